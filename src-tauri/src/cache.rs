@@ -0,0 +1,254 @@
+// src-tauri/src/cache.rs
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+use crate::engine::{EngineRequest, TextEngine};
+use crate::errors::{AiError, ClarezaError};
+use crate::jobs::JobReporter;
+use crate::retry::{generate_with_retry, RetryPolicy};
+use crate::usage::record_usage;
+use crate::utils::FileUtils;
+
+const CACHE_DIR: &str = "response_cache";
+/// Oldest entries are evicted once the cache grows past this size.
+const MAX_CACHE_BYTES: u64 = 50 * 1024 * 1024;
+const MAX_AGE_DAYS: i64 = 30;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    key: String,
+    provider: String,
+    model: String,
+    tool_id: Option<String>,
+    created_at: DateTime<Utc>,
+    prompt: String,
+    response: String,
+}
+
+/// What `list_cache_entries` reports for each entry, without the full texts.
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheEntryInfo {
+    pub key: String,
+    pub provider: String,
+    pub model: String,
+    pub tool_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub size_bytes: u64,
+    pub prompt_preview: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheStats {
+    pub entries: usize,
+    pub total_bytes: u64,
+    pub max_bytes: u64,
+    pub max_age_days: i64,
+}
+
+/// Key of a request: the hash of everything that determines the answer.
+fn cache_key(provider: &str, model: &str, prompt: &str) -> String {
+    let mut hasher = Sha256::new();
+    for part in [provider, model, prompt] {
+        hasher.update(part.as_bytes());
+        hasher.update([0u8]);
+    }
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+async fn cache_dir(app_handle: &AppHandle) -> Result<PathBuf, ClarezaError> {
+    let dir = FileUtils::app_data_path(app_handle, CACHE_DIR).await?;
+    tokio::fs::create_dir_all(&dir).await?;
+    Ok(dir)
+}
+
+fn is_expired(created_at: DateTime<Utc>) -> bool {
+    Utc::now() - created_at > Duration::days(MAX_AGE_DAYS)
+}
+
+async fn lookup(app_handle: &AppHandle, key: &str) -> Result<Option<CacheEntry>, ClarezaError> {
+    let path = cache_dir(app_handle).await?.join(format!("{}.json", key));
+    if !path.exists() {
+        return Ok(None);
+    }
+    let entry: CacheEntry = serde_json::from_str(&FileUtils::read_with_encoding(&path).await?)?;
+    if is_expired(entry.created_at) {
+        tokio::fs::remove_file(&path).await?;
+        return Ok(None);
+    }
+    Ok(Some(entry))
+}
+
+async fn store(app_handle: &AppHandle, entry: &CacheEntry) -> Result<(), ClarezaError> {
+    let dir = cache_dir(app_handle).await?;
+    let path = dir.join(format!("{}.json", entry.key));
+    FileUtils::atomic_write(&path, &serde_json::to_string(entry)?).await?;
+    enforce_limits(&dir).await
+}
+
+/// Cache files with their size and modification time, oldest first.
+async fn scan(dir: &Path) -> Result<Vec<(PathBuf, u64, DateTime<Utc>)>, ClarezaError> {
+    let mut files = Vec::new();
+    let mut entries = tokio::fs::read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+            continue;
+        }
+        let metadata = entry.metadata().await?;
+        let modified = metadata
+            .modified()
+            .map(DateTime::<Utc>::from)
+            .unwrap_or_else(|_| Utc::now());
+        files.push((path, metadata.len(), modified));
+    }
+    files.sort_by_key(|(_, _, modified)| *modified);
+    Ok(files)
+}
+
+/// Drop expired entries, then the oldest ones until the size limit holds.
+async fn enforce_limits(dir: &Path) -> Result<(), ClarezaError> {
+    let files = scan(dir).await?;
+    let mut total: u64 = files.iter().map(|(_, size, _)| size).sum();
+    for (path, size, modified) in files {
+        if total <= MAX_CACHE_BYTES && !is_expired(modified) {
+            continue;
+        }
+        tokio::fs::remove_file(&path).await?;
+        total -= size;
+    }
+    Ok(())
+}
+
+/// Run `request` through the cache: a stored answer for the same provider,
+/// model and prompt is returned without calling the engine, unless `bypass`
+/// is set. Cache failures are logged and never fail the request.
+pub async fn generate_cached(
+    engine: &dyn TextEngine,
+    request: &EngineRequest,
+    retry: &RetryPolicy,
+    bypass: bool,
+    reporter: &JobReporter,
+) -> Result<String, AiError> {
+    let app_handle = reporter.app_handle();
+    let key = cache_key(engine.name(), &request.model, &request.prompt);
+
+    if !bypass {
+        match lookup(app_handle, &key).await {
+            Ok(Some(entry)) => {
+                println!("[CACHE] Hit {} ({} / {})", key, entry.provider, entry.model);
+                reporter.output("⚡ Resposta reaproveitada do cache", "system");
+                record_usage(
+                    reporter,
+                    engine.name(),
+                    &request.model,
+                    &request.prompt,
+                    Ok(&entry.response),
+                    true,
+                    0,
+                )
+                .await;
+                return Ok(entry.response);
+            }
+            Ok(None) => {}
+            Err(e) => println!("[CACHE] Lookup failed for {}: {}", key, e),
+        }
+    }
+
+    let (response, model) = generate_with_retry(engine, request, retry, reporter).await?;
+
+    // A fallback model's answer is stored under its own key, a later request
+    // for the original model should still reach that model
+    let key = if model == request.model {
+        key
+    } else {
+        cache_key(engine.name(), &model, &request.prompt)
+    };
+    let entry = CacheEntry {
+        key,
+        provider: engine.name().to_string(),
+        model,
+        tool_id: reporter.tool_id().map(str::to_string),
+        created_at: Utc::now(),
+        prompt: request.prompt.clone(),
+        response: response.clone(),
+    };
+    if let Err(e) = store(app_handle, &entry).await {
+        println!("[CACHE] Could not store {}: {}", entry.key, e);
+    }
+    Ok(response)
+}
+
+#[tauri::command]
+pub async fn get_cache_stats(app_handle: AppHandle) -> Result<CacheStats, ClarezaError> {
+    let files = scan(&cache_dir(&app_handle).await?).await?;
+    Ok(CacheStats {
+        entries: files.len(),
+        total_bytes: files.iter().map(|(_, size, _)| size).sum(),
+        max_bytes: MAX_CACHE_BYTES,
+        max_age_days: MAX_AGE_DAYS,
+    })
+}
+
+/// Cached entries, newest first.
+#[tauri::command]
+pub async fn list_cache_entries(
+    app_handle: AppHandle,
+) -> Result<Vec<CacheEntryInfo>, ClarezaError> {
+    let files = scan(&cache_dir(&app_handle).await?).await?;
+    let mut infos = Vec::with_capacity(files.len());
+    for (path, size, _) in files.into_iter().rev() {
+        let content = FileUtils::read_with_encoding(&path).await?;
+        match serde_json::from_str::<CacheEntry>(&content) {
+            Ok(entry) => infos.push(CacheEntryInfo {
+                key: entry.key,
+                provider: entry.provider,
+                model: entry.model,
+                tool_id: entry.tool_id,
+                created_at: entry.created_at,
+                size_bytes: size,
+                prompt_preview: entry.prompt.chars().take(200).collect(),
+            }),
+            Err(e) => println!("[CACHE] Skipping unreadable {}: {}", path.display(), e),
+        }
+    }
+    Ok(infos)
+}
+
+/// Remove cached entries: the given keys, or everything older than
+/// `older_than_days`, or the whole cache. Returns how many were removed.
+#[tauri::command]
+pub async fn purge_cache(
+    app_handle: AppHandle,
+    keys: Option<Vec<String>>,
+    older_than_days: Option<i64>,
+) -> Result<usize, ClarezaError> {
+    let dir = cache_dir(&app_handle).await?;
+    let cutoff = older_than_days.map(|days| Utc::now() - Duration::days(days));
+
+    let mut removed = 0;
+    for (path, _, modified) in scan(&dir).await? {
+        let key = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or_default();
+        let selected = keys
+            .as_ref()
+            .is_none_or(|keys| keys.iter().any(|k| k == key))
+            && cutoff.is_none_or(|cutoff| modified < cutoff);
+        if selected {
+            tokio::fs::remove_file(&path).await?;
+            removed += 1;
+        }
+    }
+
+    println!("[CACHE] Purged {} entries", removed);
+    Ok(removed)
+}
@@ -344,6 +344,7 @@ pub async fn install_gemini() -> Result<crate::models::FileOperation, String> {
             path: None,
             content: None,
             metadata: None,
+            encoding: None,
         })
     } else {
         let stderr = String::from_utf8_lossy(&output.stderr).to_string();
@@ -0,0 +1,222 @@
+// src-tauri/src/chunking.rs
+
+use serde::{Deserialize, Serialize};
+
+/// Rough token estimate, about four characters per token for Portuguese prose.
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChunkingOptions {
+    /// Token budget for the document part of each request.
+    pub max_tokens: usize,
+    /// How many chunks may be sent to the engine at the same time.
+    pub max_concurrency: usize,
+}
+
+impl Default for ChunkingOptions {
+    fn default() -> Self {
+        Self {
+            max_tokens: 4000,
+            max_concurrency: 3,
+        }
+    }
+}
+
+/// A slice of the document processed by its own request.
+#[derive(Debug, Clone)]
+pub struct Chunk {
+    pub index: usize,
+    pub text: String,
+    /// Whitespace that followed the chunk in the original, restored when stitching.
+    pub separator: String,
+}
+
+/// Split Markdown into blocks: paragraphs separated by blank lines, with
+/// headings always starting a new block and fenced code kept whole. A heading
+/// stays in the same block as the paragraph after it, and each block keeps its
+/// trailing blank lines. The flag tells whether the block starts with a heading.
+fn split_blocks(content: &str) -> Vec<(String, bool)> {
+    let mut blocks: Vec<(String, bool)> = Vec::new();
+    let mut current = String::new();
+    let mut current_is_heading = false;
+    let mut only_headings = false;
+    let mut in_fence = false;
+    let mut after_blank = false;
+
+    for line in content.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        let is_fence = trimmed.starts_with("```") || trimmed.starts_with("~~~");
+        let is_heading = !in_fence && trimmed.starts_with('#');
+        let is_blank = line.trim().is_empty();
+
+        let starts_block =
+            !in_fence && !only_headings && (is_heading || (after_blank && !is_blank));
+        if starts_block && !current.is_empty() {
+            blocks.push((std::mem::take(&mut current), current_is_heading));
+        }
+        if current.is_empty() {
+            current_is_heading = is_heading;
+            only_headings = is_heading;
+        } else if !is_blank && !is_heading {
+            only_headings = false;
+        }
+
+        current.push_str(line);
+        if is_fence {
+            in_fence = !in_fence;
+        }
+        after_blank = !in_fence && is_blank;
+    }
+
+    if !current.is_empty() {
+        blocks.push((current, current_is_heading));
+    }
+    blocks
+}
+
+/// Cut a block that alone exceeds the budget, preferring sentence ends, then
+/// whitespace, and only as a last resort the middle of a word.
+fn split_oversized(block: &str, max_chars: usize) -> Vec<String> {
+    let mut pieces = Vec::new();
+    let mut rest = block;
+
+    while rest.chars().count() > max_chars {
+        let window_end = rest
+            .char_indices()
+            .nth(max_chars)
+            .map_or(rest.len(), |(idx, _)| idx);
+        let window = &rest[..window_end];
+
+        let sentence_end = window
+            .char_indices()
+            .rev()
+            .filter(|&(_, ch)| matches!(ch, '.' | '!' | '?' | '…'))
+            .filter_map(|(idx, ch)| {
+                let after = idx + ch.len_utf8();
+                let next = window[after..].chars().next()?;
+                next.is_whitespace().then(|| after + next.len_utf8())
+            })
+            .next();
+        let space = window
+            .char_indices()
+            .rev()
+            .find(|&(_, ch)| ch.is_whitespace())
+            .map(|(idx, ch)| idx + ch.len_utf8());
+
+        let cut = sentence_end
+            .or(space)
+            .filter(|&cut| cut > 0)
+            .unwrap_or(window_end);
+        pieces.push(rest[..cut].to_string());
+        rest = &rest[cut..];
+    }
+
+    if !rest.is_empty() {
+        pieces.push(rest.to_string());
+    }
+    pieces
+}
+
+/// Split a Markdown document into chunks of at most `max_tokens` (estimated).
+///
+/// Blocks are packed greedily. A heading closes the current chunk once it is
+/// at least half full, so sections tend to stay together.
+pub fn split_markdown(content: &str, max_tokens: usize) -> Vec<Chunk> {
+    let max_tokens = max_tokens.max(1);
+    let mut texts: Vec<String> = Vec::new();
+    let mut current = String::new();
+
+    for (block, is_heading) in split_blocks(content) {
+        let block_tokens = estimate_tokens(&block);
+        let current_tokens = estimate_tokens(&current);
+
+        let over_budget = current_tokens + block_tokens > max_tokens;
+        let section_break = is_heading && current_tokens * 2 >= max_tokens;
+        if !current.is_empty() && (over_budget || section_break) {
+            texts.push(std::mem::take(&mut current));
+        }
+
+        if block_tokens > max_tokens {
+            texts.extend(split_oversized(&block, max_tokens * 4));
+        } else {
+            current.push_str(&block);
+        }
+    }
+    if !current.is_empty() {
+        texts.push(current);
+    }
+
+    // Whitespace-only texts (blank lines, or a cut right before a space) are
+    // not sent, they belong to the separator of the chunk before
+    let mut chunks: Vec<Chunk> = Vec::new();
+    let mut leading = String::new();
+    for text in texts {
+        let body_len = text.trim_end().len();
+        if body_len > 0 {
+            chunks.push(Chunk {
+                index: chunks.len(),
+                separator: text[body_len..].to_string(),
+                text: format!("{}{}", std::mem::take(&mut leading), &text[..body_len]),
+            });
+        } else if let Some(last) = chunks.last_mut() {
+            last.separator.push_str(&text);
+        } else {
+            leading.push_str(&text);
+        }
+    }
+    chunks
+}
+
+/// Join per-chunk outputs in order, restoring the original separators.
+pub fn stitch(chunks: &[Chunk], outputs: &[String]) -> String {
+    chunks
+        .iter()
+        .zip(outputs)
+        .map(|(chunk, output)| format!("{}{}", output.trim_end(), chunk.separator))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(content: &str, max_tokens: usize) -> String {
+        let chunks = split_markdown(content, max_tokens);
+        let outputs: Vec<String> = chunks.iter().map(|chunk| chunk.text.clone()).collect();
+        stitch(&chunks, &outputs)
+    }
+
+    #[test]
+    fn stitching_unchanged_chunks_restores_the_document() {
+        let document = "# Título\n\nPrimeiro parágrafo.\n\n\n## Seção\n\nSegundo parágrafo, \
+com mais texto.\n\n```\ncódigo\n\nainda código\n```\n\nFim.\n";
+        for max_tokens in [1, 2, 5, 10, 4000] {
+            assert_eq!(round_trip(document, max_tokens), document, "budget {}", max_tokens);
+        }
+    }
+
+    #[test]
+    fn keeps_spaces_at_cuts() {
+        assert_eq!(round_trip("Para one. Sentence two.", 1), "Para one. Sentence two.");
+        assert_eq!(round_trip("  \n\nTexto com espaços.  ", 1), "  \n\nTexto com espaços.  ");
+    }
+
+    #[test]
+    fn respects_the_budget() {
+        let document = "Uma frase curta. ".repeat(200);
+        let chunks = split_markdown(&document, 50);
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|chunk| estimate_tokens(&chunk.text) <= 50));
+        assert!(chunks.iter().enumerate().all(|(i, chunk)| chunk.index == i));
+    }
+
+    #[test]
+    fn keeps_code_fences_whole() {
+        let document = "Texto.\n\n```\nlinha 1\n\nlinha 2\n```\n";
+        let chunks = split_markdown(document, 8);
+        assert!(chunks.iter().any(|chunk| chunk.text.contains("linha 1\n\nlinha 2")));
+    }
+}
@@ -4,12 +4,17 @@ use std::path::PathBuf;
 
 use chrono::Utc;
 use tauri::command;
+use tauri::{AppHandle, Manager};
 use tauri_plugin_dialog::DialogExt;
 use tokio::sync::oneshot;
 
+use crate::encoding::{self, LineEnding, StoredFormat};
 use crate::errors::ClarezaError;
-use crate::models::{BackupInfo, ClarezaDocument, DocumentMetadata, FileOperation, RecentFile};
+use crate::locks::acquire_document_lock;
+use crate::models::{BackupInfo, ClarezaDocument, DocumentMetadata, FileOperation};
+use crate::recent::record_recent_file;
 use crate::utils::{create_document_metadata, update_content_stats, FileUtils};
+use crate::watcher::{save_tracked, track_document, ConflictStrategy};
 
 #[command]
 pub async fn create_document(title: String) -> Result<FileOperation, ClarezaError> {
@@ -26,6 +31,7 @@ pub async fn create_document(title: String) -> Result<FileOperation, ClarezaErro
         path: None,
         content: Some(document.content),
         metadata: Some(metadata),
+        encoding: None,
     })
 }
 
@@ -54,6 +60,7 @@ pub async fn show_open_dialog(window: tauri::Window) -> Result<FileOperation, Cl
             path: None,
             content: None,
             metadata: None,
+            encoding: None,
         });
     };
 
@@ -68,18 +75,25 @@ pub async fn show_open_dialog(window: tauri::Window) -> Result<FileOperation, Cl
         path: Some(final_path.to_string_lossy().to_string()),
         content: None,
         metadata: None,
+        encoding: None,
     })
 }
 
 #[command]
-pub async fn open_document(path: String) -> Result<FileOperation, ClarezaError> {
+pub async fn open_document(
+    app_handle: AppHandle,
+    path: String,
+    read_only: Option<bool>,
+) -> Result<FileOperation, ClarezaError> {
     let safe_path = FileUtils::safe_canonicalize(&path)?;
 
     if !safe_path.exists() {
         return Err(ClarezaError::FileNotFound(path));
     }
 
-    let content = FileUtils::read_with_encoding(&safe_path).await?;
+    let bytes = tokio::fs::read(&safe_path).await?;
+    let decoded = encoding::decode(&bytes);
+    let content = encoding::normalize_line_endings(&decoded.content);
 
     // Try to parse as Clareza document first
     let (document_content, metadata) =
@@ -95,25 +109,69 @@ pub async fn open_document(path: String) -> Result<FileOperation, ClarezaError>
                     .unwrap_or("Untitled"),
             );
             update_content_stats(&mut metadata, &content);
-            (content, Some(metadata))
+            (content.clone(), Some(metadata))
         };
 
+    // Only once the document could be read, so a failed open leaves nothing held.
+    // Fails if someone else is editing it; the UI then offers read-only.
+    let read_only = read_only.unwrap_or(false);
+    if !read_only {
+        acquire_document_lock(&safe_path)?;
+    }
+    encoding::remember_format(
+        &safe_path,
+        StoredFormat {
+            encoding: decoded.encoding,
+            bom: decoded.bom,
+            line_ending: LineEnding::detect(&decoded.content).unwrap_or_default(),
+        },
+    );
+    track_document(&app_handle, &safe_path, &bytes, &content).await;
+
+    if let Some(metadata) = &metadata {
+        record_recent_file(&app_handle, &safe_path, &metadata.title).await;
+    }
+
     Ok(FileOperation {
         success: true,
-        message: format!("File opened: {}", safe_path.display()),
+        message: if read_only {
+            format!("File opened read-only: {}", safe_path.display())
+        } else {
+            format!("File opened: {}", safe_path.display())
+        },
         path: Some(safe_path.to_string_lossy().to_string()),
         content: Some(document_content),
         metadata,
+        encoding: Some(decoded.encoding.name().to_string()),
     })
 }
 
 #[command]
 pub async fn save_document(
+    app_handle: AppHandle,
     path: String,
     content: String,
     metadata: Option<DocumentMetadata>,
+    on_conflict: Option<ConflictStrategy>,
+    encoding: Option<String>,
 ) -> Result<FileOperation, ClarezaError> {
     let safe_path = PathBuf::from(&path);
+    let is_clareza = safe_path.extension().and_then(|ext| ext.to_str()) == Some("clareza");
+
+    // A document opened read-only can only be saved once its holder closes it
+    acquire_document_lock(&safe_path)?;
+
+    // Line endings are restored below, as recorded when the file was opened
+    let format = encoding::remembered_format(&safe_path);
+
+    // Saved back in the encoding it was opened with, or converted to the one
+    // requested. Clareza documents are JSON and always UTF-8.
+    let target_encoding = match encoding.as_deref() {
+        _ if is_clareza => encoding_rs::UTF_8,
+        Some(label) => encoding::encoding_for_label(label)?,
+        None => format.encoding,
+    };
+    let content = encoding::normalize_line_endings(&content);
 
     let mut doc_metadata = metadata.unwrap_or_else(|| {
         create_document_metadata(
@@ -126,7 +184,7 @@ pub async fn save_document(
 
     update_content_stats(&mut doc_metadata, &content);
 
-    let final_content = if safe_path.extension().and_then(|ext| ext.to_str()) == Some("clareza") {
+    let final_content = if is_clareza {
         let document = ClarezaDocument {
             metadata: doc_metadata.clone(),
             content: content.clone(),
@@ -137,7 +195,39 @@ pub async fn save_document(
         content
     };
 
-    FileUtils::atomic_write(&safe_path, &final_content).await?;
+    // Keep the BOM the file had, UTF-16 cannot be recognised again without one
+    let bom = format.bom
+        || target_encoding == encoding_rs::UTF_16LE
+        || target_encoding == encoding_rs::UTF_16BE;
+
+    // Refuses, or merges, if another program changed the file since it was opened
+    let written = save_tracked(
+        &app_handle,
+        &safe_path,
+        &final_content,
+        on_conflict.unwrap_or_default(),
+        |text| encoding::encode(&format.line_ending.apply(text), target_encoding, bom),
+    )
+    .await?;
+    let merged_content = if written == final_content {
+        None
+    } else if is_clareza {
+        let document: ClarezaDocument = serde_json::from_str(&written)?;
+        doc_metadata = document.metadata;
+        Some(document.content)
+    } else {
+        update_content_stats(&mut doc_metadata, &written);
+        Some(written.clone())
+    };
+    let final_content = written;
+    encoding::remember_format(
+        &safe_path,
+        StoredFormat {
+            encoding: target_encoding,
+            bom,
+            line_ending: format.line_ending,
+        },
+    );
 
     // Check if we should create a new version
     let versions_dir = FileUtils::get_versions_dir(&safe_path)?;
@@ -167,12 +257,24 @@ pub async fn save_document(
         tokio::fs::write(version_path, &final_content).await?;
     }
 
+    record_recent_file(&app_handle, &safe_path, &doc_metadata.title).await;
+
+    // After a merge the editor must show what was actually written
+    let message = if merged_content.is_some() {
+        format!(
+            "File merged with external changes and saved: {}",
+            safe_path.display()
+        )
+    } else {
+        format!("File saved: {}", safe_path.display())
+    };
     Ok(FileOperation {
         success: true,
-        message: format!("File saved: {}", safe_path.display()),
+        message,
         path: Some(safe_path.to_string_lossy().to_string()),
-        content: None,
+        content: merged_content,
         metadata: Some(doc_metadata),
+        encoding: Some(target_encoding.name().to_string()),
     })
 }
 
@@ -182,6 +284,7 @@ pub async fn save_document_as(
     content: String,
     suggested_name: Option<String>,
     metadata: Option<DocumentMetadata>,
+    encoding: Option<String>,
 ) -> Result<FileOperation, ClarezaError> {
     let mut dialog = window
         .dialog()
@@ -221,7 +324,16 @@ pub async fn save_document_as(
     }
 
     // Reuse save logic (will save as plain content since extension is .md)
-    save_document(final_path.to_string_lossy().to_string(), content, metadata).await
+    // The dialog already asked before replacing an existing file
+    save_document(
+        window.app_handle().clone(),
+        final_path.to_string_lossy().to_string(),
+        content,
+        metadata,
+        Some(ConflictStrategy::Overwrite),
+        encoding,
+    )
+    .await
 }
 
 #[command]
@@ -291,6 +403,7 @@ pub async fn list_backups(original_path: String) -> Result<Vec<BackupInfo>, Clar
 
 #[command]
 pub async fn restore_backup(
+    app_handle: AppHandle,
     backup_path: String,
     target_path: String,
 ) -> Result<FileOperation, ClarezaError> {
@@ -301,14 +414,36 @@ pub async fn restore_backup(
         return Err(ClarezaError::FileNotFound(backup_path));
     }
 
+    // Same as saving: not while someone else is editing the document
+    acquire_document_lock(&safe_target_path)?;
+
     // Create backup of current file before restoring
     if safe_target_path.exists() {
         let current_backup = FileUtils::create_backup_path(&safe_target_path)?;
         tokio::fs::copy(&safe_target_path, current_backup).await?;
     }
 
-    // Restore from backup
-    tokio::fs::copy(&safe_backup_path, &safe_target_path).await?;
+    // Restore from backup, byte for byte. Written as a tracked save so the
+    // watcher does not report it as an external change.
+    let bytes = tokio::fs::read(&safe_backup_path).await?;
+    let decoded = encoding::decode(&bytes);
+    let content = encoding::normalize_line_endings(&decoded.content);
+    save_tracked(
+        &app_handle,
+        &safe_target_path,
+        &content,
+        ConflictStrategy::Overwrite,
+        |_| Ok(bytes.clone()),
+    )
+    .await?;
+    encoding::remember_format(
+        &safe_target_path,
+        StoredFormat {
+            encoding: decoded.encoding,
+            bom: decoded.bom,
+            line_ending: LineEnding::detect(&decoded.content).unwrap_or_default(),
+        },
+    );
 
     Ok(FileOperation {
         success: true,
@@ -316,6 +451,7 @@ pub async fn restore_backup(
         path: Some(safe_target_path.to_string_lossy().to_string()),
         content: None,
         metadata: None,
+        encoding: None,
     })
 }
 
@@ -395,14 +531,10 @@ pub async fn get_document_version(
         path: Some(safe_path.to_string_lossy().to_string()),
         content: Some(content),
         metadata: Some(metadata),
+        encoding: None,
     })
 }
 
-#[command]
-pub async fn get_recent_files() -> Result<Vec<RecentFile>, ClarezaError> {
-    Ok(Vec::new())
-}
-
 #[command]
 pub async fn validate_path(path: String) -> Result<(), ClarezaError> {
     FileUtils::safe_canonicalize(&path)?;
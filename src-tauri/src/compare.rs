@@ -0,0 +1,209 @@
+// src-tauri/src/compare.rs
+
+use futures_util::future::join_all;
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+use tauri::ipc::Channel;
+use tauri::AppHandle;
+
+use crate::engine::{EngineRequest, ProviderConfig, ENGINE_STATE};
+use crate::errors::ClarezaError;
+use crate::gemini::{build_prompt, PromptOptions};
+use crate::jobs::{JobEvent, JobReporter};
+use crate::pipeline::RequestPipeline;
+use crate::templates::Template;
+use crate::usage::record_usage;
+
+/// One side of a comparison: a model, optionally on another provider than
+/// the active one.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ModelCandidate {
+    pub provider: Option<ProviderConfig>,
+    pub model: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ComparisonResult {
+    pub job_id: String,
+    pub provider: String,
+    pub model: String,
+    pub content: Option<String>,
+    pub error: Option<String>,
+    pub error_kind: Option<String>,
+    pub duration_ms: u64,
+    pub output_chars: usize,
+    pub output_words: usize,
+    /// Output length relative to the input document, in characters.
+    pub length_ratio: Option<f64>,
+    pub injection_warning: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ComparisonReport {
+    pub input_chars: usize,
+    pub input_words: usize,
+    pub results: Vec<ComparisonResult>,
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn run_candidate(
+    app_handle: AppHandle,
+    candidate: ModelCandidate,
+    prompt: String,
+    pipeline: &RequestPipeline,
+    sources: &[&str],
+    input_chars: usize,
+    tool_id: Option<String>,
+    on_event: Option<Channel<JobEvent>>,
+) -> ComparisonResult {
+    let provider = match candidate.provider {
+        Some(provider) => provider,
+        None => ENGINE_STATE.provider.read().await.clone(),
+    };
+    let reporter = JobReporter::new(
+        app_handle,
+        tool_id,
+        provider.name(),
+        &candidate.model,
+        on_event,
+    );
+    reporter.started();
+    pipeline.report_masking(&reporter);
+
+    // Single attempt and no cache, so latencies are comparable
+    let started = Instant::now();
+    let request = EngineRequest {
+        prompt,
+        model: candidate.model.clone(),
+        timeout_secs: 180,
+        document_tools: pipeline.document_tools(),
+    };
+    let result = match provider.build() {
+        Ok(engine) => engine.generate(&request, reporter.clone()).await,
+        Err(e) => Err(e),
+    };
+    let duration_ms = started.elapsed().as_millis() as u64;
+    record_usage(
+        &reporter,
+        provider.name(),
+        &request.model,
+        &request.prompt,
+        result.as_deref(),
+        false,
+        duration_ms,
+    )
+    .await;
+
+    let (checked, error) = match result {
+        Ok(response) => (Some(pipeline.finish(&response, sources, None, &reporter)), None),
+        Err(e) => {
+            println!(
+                "[COMPARE] {} / {} failed: {}",
+                provider.name(),
+                candidate.model,
+                e
+            );
+            (None, Some(e))
+        }
+    };
+    let (content, injection_warning) = match checked {
+        Some(checked) => (Some(checked.content), checked.injection_warning),
+        None => (None, None),
+    };
+    let text = content.as_deref().unwrap_or_default();
+    let output_chars = text.chars().count();
+    reporter.output(
+        format!(
+            "📊 {} / {}: {} caracteres em {:.1}s",
+            provider.name(),
+            candidate.model,
+            output_chars,
+            duration_ms as f64 / 1000.0
+        ),
+        "system",
+    );
+
+    ComparisonResult {
+        job_id: reporter.job_id().to_string(),
+        provider: provider.name().to_string(),
+        model: candidate.model,
+        output_chars,
+        output_words: text.split_whitespace().count(),
+        length_ratio: (content.is_some() && input_chars > 0)
+            .then(|| output_chars as f64 / input_chars as f64),
+        content,
+        error_kind: error.as_ref().map(|e| e.kind().to_string()),
+        error: error.map(|e| e.to_string()),
+        duration_ms,
+        injection_warning,
+    }
+}
+
+/// Run the same prompt on the same content against several models at once
+/// and return every answer with its latency and length.
+///
+/// Each candidate reports progress as its own job, but no completion event
+/// is emitted: the results are only returned here, so they never replace the
+/// open document. Documents are sent whole, without chunking.
+#[tauri::command]
+pub async fn compare_models(
+    app_handle: AppHandle,
+    prompt: String,
+    file_content: Option<String>,
+    candidates: Vec<ModelCandidate>,
+    tool_id: Option<String>,
+    options: Option<PromptOptions>,
+    on_event: Option<Channel<JobEvent>>,
+) -> Result<ComparisonReport, ClarezaError> {
+    if candidates.len() < 2 {
+        return Err(ClarezaError::InvalidFormat(
+            "At least two models are needed for a comparison".to_string(),
+        ));
+    }
+    if candidates.iter().any(|c| c.model.trim().is_empty()) {
+        return Err(ClarezaError::InvalidFormat(
+            "Model name cannot be empty".to_string(),
+        ));
+    }
+
+    let options = options.unwrap_or_default();
+    let template = Template::parse(&prompt)?;
+
+    // Every candidate gets the same masked prompt
+    let mut pipeline = RequestPipeline::new(options.redaction.as_ref())?;
+    let original_variables = options.variables.unwrap_or_default();
+    let variables = pipeline.redact_variables(&original_variables);
+    let sent_content = file_content.as_deref().map(|content| pipeline.redact(content));
+    let full_prompt = build_prompt(&template, &variables, sent_content.as_deref());
+    let input = file_content.as_deref().unwrap_or_default();
+    let input_chars = input.chars().count();
+    println!(
+        "[COMPARE] Comparing {} models on {} chars",
+        candidates.len(),
+        input_chars
+    );
+
+    let sources = [
+        input,
+        original_variables.selection.as_deref().unwrap_or_default(),
+    ];
+    let runs = candidates.into_iter().map(|candidate| {
+        run_candidate(
+            app_handle.clone(),
+            candidate,
+            full_prompt.clone(),
+            &pipeline,
+            &sources,
+            input_chars,
+            tool_id.clone(),
+            on_event.clone(),
+        )
+    });
+    let results = join_all(runs).await;
+
+    Ok(ComparisonReport {
+        input_chars,
+        input_words: input.split_whitespace().count(),
+        results,
+    })
+}
@@ -0,0 +1,486 @@
+// src-tauri/src/diff.rs
+
+use serde::{Deserialize, Serialize};
+use similar::{capture_diff_slices_deadline, Algorithm, DiffTag};
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+/// Give up refining the diff after this long and fall back to coarser hunks.
+const DIFF_DEADLINE: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffGranularity {
+    Word,
+    Sentence,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HunkKind {
+    Insert,
+    Delete,
+    Replace,
+}
+
+/// Span of a text in UTF-8 bytes and in UTF-16 code units. The `char_*`
+/// offsets are what JavaScript string indices, and so the editor, use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextRange {
+    pub byte_start: usize,
+    pub byte_end: usize,
+    pub char_start: usize,
+    pub char_end: usize,
+}
+
+/// Length of `text` in UTF-16 code units, see `TextRange`.
+pub fn utf16_len(text: &str) -> usize {
+    text.chars().map(char::len_utf16).sum()
+}
+
+/// Byte offset of a position given in UTF-16 code units, or `None` past the
+/// end or inside a surrogate pair.
+pub fn byte_offset(text: &str, utf16_offset: usize) -> Option<usize> {
+    let mut units = 0;
+    for (idx, ch) in text.char_indices() {
+        if units >= utf16_offset {
+            return (units == utf16_offset).then_some(idx);
+        }
+        units += ch.len_utf16();
+    }
+    (units == utf16_offset).then_some(text.len())
+}
+
+/// One reviewable change between the original and the rewritten text.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiffHunk {
+    pub kind: HunkKind,
+    pub original: TextRange,
+    pub revised: TextRange,
+    pub original_text: String,
+    pub revised_text: String,
+}
+
+/// Split `text` into contiguous tokens, returned as byte ranges.
+///
+/// Word mode yields runs of alphanumerics, runs of whitespace and single
+/// punctuation characters. Sentence mode cuts after `.`, `!`, `?` or `…`
+/// followed by whitespace, and at line breaks, keeping the separator with
+/// the preceding sentence.
+fn tokenize(text: &str, granularity: DiffGranularity) -> Vec<(usize, usize)> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+
+    match granularity {
+        DiffGranularity::Word => {
+            while let Some((idx, ch)) = chars.next() {
+                let same_class = |next: char| {
+                    (ch.is_alphanumeric() && next.is_alphanumeric())
+                        || (ch.is_whitespace() && next.is_whitespace())
+                };
+                match chars.peek() {
+                    Some(&(_, next)) if same_class(next) => continue,
+                    Some(&(next_idx, _)) => {
+                        tokens.push((start, next_idx));
+                        start = next_idx;
+                    }
+                    None => tokens.push((start, idx + ch.len_utf8())),
+                }
+            }
+        }
+        DiffGranularity::Sentence => {
+            let mut after_terminator = false;
+            while let Some((idx, ch)) = chars.next() {
+                let end = idx + ch.len_utf8();
+                if ch == '\n' {
+                    tokens.push((start, end));
+                    start = end;
+                    after_terminator = false;
+                    continue;
+                }
+                if matches!(ch, '.' | '!' | '?' | '…') {
+                    after_terminator = true;
+                    continue;
+                }
+                if after_terminator && ch.is_whitespace() {
+                    // Swallow the run of spaces into the sentence it ends
+                    let mut cut = end;
+                    while let Some(&(next_idx, next)) = chars.peek() {
+                        if next == '\n' || !next.is_whitespace() {
+                            break;
+                        }
+                        cut = next_idx + next.len_utf8();
+                        chars.next();
+                    }
+                    tokens.push((start, cut));
+                    start = cut;
+                }
+                after_terminator = false;
+            }
+            if start < text.len() {
+                tokens.push((start, text.len()));
+            }
+        }
+    }
+
+    tokens
+}
+
+/// Converts byte offsets to UTF-16 offsets for monotonically increasing input.
+struct CharCounter<'a> {
+    text: &'a str,
+    byte_pos: usize,
+    char_pos: usize,
+}
+
+impl<'a> CharCounter<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            text,
+            byte_pos: 0,
+            char_pos: 0,
+        }
+    }
+
+    fn char_offset(&mut self, byte_offset: usize) -> usize {
+        if byte_offset < self.byte_pos {
+            self.byte_pos = 0;
+            self.char_pos = 0;
+        }
+        self.char_pos += utf16_len(&self.text[self.byte_pos..byte_offset]);
+        self.byte_pos = byte_offset;
+        self.char_pos
+    }
+
+    fn range(&mut self, byte_start: usize, byte_end: usize) -> TextRange {
+        TextRange {
+            byte_start,
+            byte_end,
+            char_start: self.char_offset(byte_start),
+            char_end: self.char_offset(byte_end),
+        }
+    }
+}
+
+/// Byte range covered by `tokens[range]`, or an empty range at the position
+/// where those tokens would be.
+fn span(tokens: &[(usize, usize)], range: Range<usize>, text_len: usize) -> (usize, usize) {
+    if range.is_empty() {
+        let at = tokens.get(range.start).map_or(text_len, |t| t.0);
+        (at, at)
+    } else {
+        (tokens[range.start].0, tokens[range.end - 1].1)
+    }
+}
+
+/// Compute the hunks turning `original` into `revised`.
+///
+/// In word mode, changes separated only by spaces are merged, so rewording a
+/// phrase shows up as one replacement instead of one per word.
+pub fn diff_texts(original: &str, revised: &str, granularity: DiffGranularity) -> Vec<DiffHunk> {
+    let old_tokens = tokenize(original, granularity);
+    let new_tokens = tokenize(revised, granularity);
+    let old_words: Vec<&str> = old_tokens.iter().map(|&(s, e)| &original[s..e]).collect();
+    let new_words: Vec<&str> = new_tokens.iter().map(|&(s, e)| &revised[s..e]).collect();
+
+    let ops = capture_diff_slices_deadline(
+        Algorithm::Myers,
+        &old_words,
+        &new_words,
+        Some(Instant::now() + DIFF_DEADLINE),
+    );
+
+    // Collect changed token ranges, merging across whitespace-only equal runs
+    // in word mode. `mergeable` is true right after a change and stays true
+    // while the following equal run is blank.
+    let mut changes: Vec<(Range<usize>, Range<usize>)> = Vec::new();
+    let mut mergeable = false;
+    for op in ops {
+        let (tag, old_range, new_range) = op.as_tag_tuple();
+        if tag == DiffTag::Equal {
+            mergeable = granularity == DiffGranularity::Word
+                && old_words[old_range]
+                    .iter()
+                    .all(|w| w.trim().is_empty() && !w.contains('\n'));
+            continue;
+        }
+        match changes.last_mut() {
+            Some((old_prev, new_prev)) if mergeable => {
+                old_prev.end = old_range.end;
+                new_prev.end = new_range.end;
+            }
+            _ => changes.push((old_range, new_range)),
+        }
+        mergeable = true;
+    }
+
+    let mut old_counter = CharCounter::new(original);
+    let mut new_counter = CharCounter::new(revised);
+    changes
+        .into_iter()
+        .map(|(old_range, new_range)| {
+            let kind = match (old_range.is_empty(), new_range.is_empty()) {
+                (true, _) => HunkKind::Insert,
+                (_, true) => HunkKind::Delete,
+                _ => HunkKind::Replace,
+            };
+            let (old_start, old_end) = span(&old_tokens, old_range, original.len());
+            let (new_start, new_end) = span(&new_tokens, new_range, revised.len());
+            DiffHunk {
+                kind,
+                original: old_counter.range(old_start, old_end),
+                revised: new_counter.range(new_start, new_end),
+                original_text: original[old_start..old_end].to_string(),
+                revised_text: revised[new_start..new_end].to_string(),
+            }
+        })
+        .collect()
+}
+
+/// Result of a three-way merge. When `conflicts` is non-zero, `merged`
+/// contains both versions of each conflicting region between
+/// `<<<<<<<`, `=======` and `>>>>>>>` lines.
+#[derive(Debug, Clone, Serialize)]
+pub struct MergeResult {
+    pub merged: String,
+    pub conflicts: usize,
+}
+
+/// Lines of `base` replaced by other lines, as found by the line diff.
+struct LineChange<'a> {
+    base: Range<usize>,
+    lines: &'a [&'a str],
+}
+
+fn line_changes<'a>(base: &[&str], other: &'a [&'a str]) -> Vec<LineChange<'a>> {
+    capture_diff_slices_deadline(
+        Algorithm::Myers,
+        base,
+        other,
+        Some(Instant::now() + DIFF_DEADLINE),
+    )
+    .into_iter()
+    .map(|op| op.as_tag_tuple())
+    .filter(|(tag, _, _)| *tag != DiffTag::Equal)
+    .map(|(_, old_range, new_range)| LineChange {
+        base: old_range,
+        lines: &other[new_range],
+    })
+    .collect()
+}
+
+/// `base[region]` with `changes`, all inside the region, applied.
+fn apply_changes(base: &[&str], region: Range<usize>, changes: &[&LineChange]) -> String {
+    let mut result = String::new();
+    let mut pos = region.start;
+    for change in changes {
+        result.extend(base[pos..change.base.start].iter().copied());
+        result.extend(change.lines.iter().copied());
+        pos = change.base.end;
+    }
+    result.extend(base[pos..region.end].iter().copied());
+    result
+}
+
+fn push_conflict_side(merged: &mut String, text: &str) {
+    merged.push_str(text);
+    if !text.is_empty() && !text.ends_with('\n') {
+        merged.push('\n');
+    }
+}
+
+/// Line-based three-way merge of two versions derived from `base`.
+///
+/// Changes from both sides are kept when they touch different lines. Regions
+/// both sides changed, or changes that touch each other, are conflicts unless
+/// both sides made the same edit.
+pub fn merge_texts(base: &str, ours: &str, theirs: &str, ours_label: &str, theirs_label: &str) -> MergeResult {
+    let base_lines: Vec<&str> = base.split_inclusive('\n').collect();
+    let our_lines: Vec<&str> = ours.split_inclusive('\n').collect();
+    let their_lines: Vec<&str> = theirs.split_inclusive('\n').collect();
+    let our_changes = line_changes(&base_lines, &our_lines);
+    let their_changes = line_changes(&base_lines, &their_lines);
+
+    let mut merged = String::with_capacity(ours.len().max(theirs.len()));
+    let mut conflicts = 0;
+    let mut pos = 0;
+    let (mut i, mut j) = (0, 0);
+    while i < our_changes.len() || j < their_changes.len() {
+        // Start a region at the earliest change, then absorb every change of
+        // either side that overlaps or touches it
+        let mut region = match (our_changes.get(i), their_changes.get(j)) {
+            (Some(a), Some(b)) => a.base.start.min(b.base.start)..a.base.start.min(b.base.start),
+            (Some(a), None) => a.base.start..a.base.start,
+            (None, Some(b)) => b.base.start..b.base.start,
+            (None, None) => break,
+        };
+        let (mut ours_in, mut theirs_in) = (Vec::new(), Vec::new());
+        loop {
+            if let Some(change) = our_changes.get(i).filter(|c| c.base.start <= region.end) {
+                region.end = region.end.max(change.base.end);
+                ours_in.push(change);
+                i += 1;
+            } else if let Some(change) = their_changes.get(j).filter(|c| c.base.start <= region.end) {
+                region.end = region.end.max(change.base.end);
+                theirs_in.push(change);
+                j += 1;
+            } else {
+                break;
+            }
+        }
+
+        merged.extend(base_lines[pos..region.start].iter().copied());
+        let our_text = apply_changes(&base_lines, region.clone(), &ours_in);
+        let their_text = apply_changes(&base_lines, region.clone(), &theirs_in);
+        if theirs_in.is_empty() || our_text == their_text {
+            merged.push_str(&our_text);
+        } else if ours_in.is_empty() {
+            merged.push_str(&their_text);
+        } else {
+            conflicts += 1;
+            merged.push_str(&format!("<<<<<<< {}\n", ours_label));
+            push_conflict_side(&mut merged, &our_text);
+            merged.push_str("=======\n");
+            push_conflict_side(&mut merged, &their_text);
+            merged.push_str(&format!(">>>>>>> {}\n", theirs_label));
+        }
+        pos = region.end;
+    }
+    merged.extend(base_lines[pos..].iter().copied());
+
+    MergeResult { merged, conflicts }
+}
+
+#[tauri::command]
+pub fn diff_documents(
+    original: String,
+    revised: String,
+    granularity: Option<DiffGranularity>,
+) -> Result<Vec<DiffHunk>, String> {
+    Ok(diff_texts(
+        &original,
+        &revised,
+        granularity.unwrap_or(DiffGranularity::Word),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Replace every hunk's original range with its revised text.
+    fn apply(original: &str, hunks: &[DiffHunk]) -> String {
+        let mut result = original.to_string();
+        for hunk in hunks.iter().rev() {
+            result.replace_range(
+                hunk.original.byte_start..hunk.original.byte_end,
+                &hunk.revised_text,
+            );
+        }
+        result
+    }
+
+    #[test]
+    fn hunks_turn_the_original_into_the_revision() {
+        let original = "O menino foi na escola.\nEle gostou muito.\n";
+        let revised = "O menino foi à escola.\nEle gostou bastante da aula.\nFim.\n";
+        for granularity in [DiffGranularity::Word, DiffGranularity::Sentence] {
+            let hunks = diff_texts(original, revised, granularity);
+            assert!(!hunks.is_empty());
+            assert_eq!(apply(original, &hunks), revised);
+        }
+    }
+
+    #[test]
+    fn classifies_hunks() {
+        let hunks = diff_texts("um dois", "um dois três", DiffGranularity::Word);
+        assert_eq!(hunks.len(), 1);
+        assert_eq!(hunks[0].kind, HunkKind::Insert);
+
+        let hunks = diff_texts("um dois três", "um três", DiffGranularity::Word);
+        assert_eq!(hunks.len(), 1);
+        assert_eq!(hunks[0].kind, HunkKind::Delete);
+
+        assert!(diff_texts("igual", "igual", DiffGranularity::Word).is_empty());
+    }
+
+    #[test]
+    fn merges_changed_words_separated_by_spaces() {
+        let hunks = diff_texts("a casa velha caiu", "a moradia antiga caiu", DiffGranularity::Word);
+        assert_eq!(hunks.len(), 1);
+        assert_eq!(hunks[0].original_text, "casa velha");
+        assert_eq!(hunks[0].revised_text, "moradia antiga");
+    }
+
+    #[test]
+    fn converts_utf16_offsets_to_bytes() {
+        let text = "ação 😀 fim";
+        assert_eq!(byte_offset(text, 0), Some(0));
+        assert_eq!(byte_offset(text, 2), Some("aç".len()));
+        assert_eq!(byte_offset(text, 5), Some("ação ".len()));
+        // Inside the surrogate pair of the emoji
+        assert_eq!(byte_offset(text, 6), None);
+        assert_eq!(byte_offset(text, 7), Some("ação 😀".len()));
+        assert_eq!(byte_offset(text, utf16_len(text)), Some(text.len()));
+        assert_eq!(byte_offset(text, utf16_len(text) + 1), None);
+    }
+
+    #[test]
+    fn hunk_offsets_count_utf16_units() {
+        let hunks = diff_texts("😀 um texto", "😀 um teste", DiffGranularity::Word);
+        assert_eq!(hunks.len(), 1);
+        let original = hunks[0].original;
+        assert_eq!(original.char_start, 6);
+        assert_eq!(original.char_end, 11);
+        assert_eq!(&"😀 um texto"[original.byte_start..original.byte_end], "texto");
+    }
+
+    const BASE: &str = "um\ndois\ntrês\nquatro\ncinco\n";
+
+    fn merge(ours: &str, theirs: &str) -> MergeResult {
+        merge_texts(BASE, ours, theirs, "Clareza", "Disco")
+    }
+
+    #[test]
+    fn merges_changes_to_different_lines() {
+        let result = merge("UM\ndois\ntrês\nquatro\ncinco\n", "um\ndois\ntrês\nquatro\nCINCO\n");
+        assert_eq!(result.conflicts, 0);
+        assert_eq!(result.merged, "UM\ndois\ntrês\nquatro\nCINCO\n");
+    }
+
+    #[test]
+    fn identical_edits_do_not_conflict() {
+        let edited = "um\ndois\nTRÊS\nquatro\ncinco\n";
+        let result = merge(edited, edited);
+        assert_eq!(result.conflicts, 0);
+        assert_eq!(result.merged, edited);
+    }
+
+    #[test]
+    fn keeps_one_sided_insertions_and_deletions() {
+        let result = merge(
+            "um\ndois\ntrês\nquatro\ncinco\nseis\n",
+            "dois\ntrês\nquatro\ncinco\n",
+        );
+        assert_eq!(result.conflicts, 0);
+        assert_eq!(result.merged, "dois\ntrês\nquatro\ncinco\nseis\n");
+    }
+
+    #[test]
+    fn marks_conflicting_edits() {
+        let result = merge("um\ndois\nTRES\nquatro\ncinco\n", "um\ndois\n3\nquatro\ncinco\n");
+        assert_eq!(result.conflicts, 1);
+        assert_eq!(
+            result.merged,
+            "um\ndois\n<<<<<<< Clareza\nTRES\n=======\n3\n>>>>>>> Disco\nquatro\ncinco\n"
+        );
+    }
+
+    #[test]
+    fn conflict_sides_end_with_a_line_break() {
+        let result = merge_texts("fim", "fim!", "fim?", "Clareza", "Disco");
+        assert_eq!(result.conflicts, 1);
+        assert_eq!(result.merged, "<<<<<<< Clareza\nfim!\n=======\nfim?\n>>>>>>> Disco\n");
+    }
+}
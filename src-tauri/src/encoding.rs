@@ -0,0 +1,276 @@
+// src-tauri/src/encoding.rs
+
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::errors::ClarezaError;
+use crate::utils::FileUtils;
+
+/// Encoding, BOM and line endings of each document read, so saving writes
+/// them back the same way unless the UI asks for another encoding.
+static STORED_FORMATS: Lazy<Mutex<HashMap<PathBuf, StoredFormat>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+    Cr,
+}
+
+impl LineEnding {
+    /// The style used by most lines, `None` without any line break.
+    pub fn detect(text: &str) -> Option<LineEnding> {
+        let crlf = text.matches("\r\n").count();
+        let cr = text.matches('\r').count() - crlf;
+        let lf = text.matches('\n').count() - crlf;
+        if crlf + cr + lf == 0 {
+            None
+        } else if crlf >= lf && crlf >= cr {
+            Some(LineEnding::CrLf)
+        } else if cr > lf {
+            Some(LineEnding::Cr)
+        } else {
+            Some(LineEnding::Lf)
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+            LineEnding::Cr => "\r",
+        }
+    }
+
+    /// Convert text with `\n` line breaks to this style.
+    pub fn apply(self, text: &str) -> String {
+        match self {
+            LineEnding::Lf => text.to_string(),
+            _ => text.replace('\n', self.as_str()),
+        }
+    }
+}
+
+/// The editor, diffs and merges work with `\n` only.
+pub fn normalize_line_endings(text: &str) -> String {
+    if text.contains('\r') {
+        text.replace("\r\n", "\n").replace('\r', "\n")
+    } else {
+        text.to_string()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct StoredFormat {
+    pub encoding: &'static Encoding,
+    pub bom: bool,
+    pub line_ending: LineEnding,
+}
+
+impl Default for StoredFormat {
+    fn default() -> Self {
+        Self {
+            encoding: UTF_8,
+            bom: false,
+            line_ending: LineEnding::default(),
+        }
+    }
+}
+
+pub fn remember_format(path: &Path, format: StoredFormat) {
+    if let Ok(mut formats) = STORED_FORMATS.lock() {
+        formats.insert(FileUtils::document_key(path), format);
+    }
+}
+
+/// How `path` was stored when last read or written, UTF-8 with LF and no
+/// BOM for a document Clareza has not seen.
+pub fn remembered_format(path: &Path) -> StoredFormat {
+    STORED_FORMATS
+        .lock()
+        .ok()
+        .and_then(|formats| formats.get(&FileUtils::document_key(path)).copied())
+        .unwrap_or_default()
+}
+
+/// Text read from disk together with how it was stored, so it can be written
+/// back the same way.
+#[derive(Debug, Clone)]
+pub struct DecodedText {
+    pub content: String,
+    pub encoding: &'static Encoding,
+    pub bom: bool,
+}
+
+/// Decode file contents: a BOM wins, then UTF-8, then Windows-1252.
+///
+/// Without a BOM only UTF-8 can be recognised reliably. Anything else is
+/// taken as Windows-1252, the usual encoding of older Portuguese documents
+/// (Word exports, Notepad before Windows 10) and a superset of ISO-8859-1 for
+/// every printable character.
+pub fn decode(bytes: &[u8]) -> DecodedText {
+    if let Some((encoding, bom_length)) = Encoding::for_bom(bytes) {
+        let (content, _) = encoding.decode_without_bom_handling(&bytes[bom_length..]);
+        return DecodedText {
+            content: content.into_owned(),
+            encoding,
+            bom: true,
+        };
+    }
+
+    match std::str::from_utf8(bytes) {
+        Ok(content) => DecodedText {
+            content: content.to_string(),
+            encoding: UTF_8,
+            bom: false,
+        },
+        Err(_) => {
+            let (content, _) = WINDOWS_1252.decode_without_bom_handling(bytes);
+            DecodedText {
+                content: content.into_owned(),
+                encoding: WINDOWS_1252,
+                bom: false,
+            }
+        }
+    }
+}
+
+/// Look up an encoding by any of its WHATWG labels ("latin1", "cp1252",
+/// "utf-16le", ...).
+pub fn encoding_for_label(label: &str) -> Result<&'static Encoding, ClarezaError> {
+    Encoding::for_label(label.trim().as_bytes())
+        .ok_or_else(|| ClarezaError::InvalidFormat(format!("Unknown encoding '{}'", label)))
+}
+
+fn bom_for(encoding: &'static Encoding) -> &'static [u8] {
+    if encoding == UTF_8 {
+        b"\xEF\xBB\xBF"
+    } else if encoding == UTF_16LE {
+        b"\xFF\xFE"
+    } else if encoding == UTF_16BE {
+        b"\xFE\xFF"
+    } else {
+        b""
+    }
+}
+
+/// Encode `content` for writing to disk.
+///
+/// Characters the encoding cannot represent are an error rather than being
+/// replaced, the user has to choose an encoding that keeps the text intact.
+pub fn encode(
+    content: &str,
+    encoding: &'static Encoding,
+    bom: bool,
+) -> Result<Vec<u8>, ClarezaError> {
+    let mut bytes = Vec::with_capacity(content.len() + 3);
+    if bom {
+        bytes.extend_from_slice(bom_for(encoding));
+    }
+
+    // encoding_rs only decodes UTF-16
+    if encoding == UTF_16LE {
+        bytes.extend(content.encode_utf16().flat_map(u16::to_le_bytes));
+        return Ok(bytes);
+    }
+    if encoding == UTF_16BE {
+        bytes.extend(content.encode_utf16().flat_map(u16::to_be_bytes));
+        return Ok(bytes);
+    }
+
+    let (encoded, _, had_errors) = encoding.encode(content);
+    if had_errors {
+        let unmappable = content
+            .chars()
+            .find(|c| encoding.encode(c.encode_utf8(&mut [0; 4])).2)
+            .unwrap_or(char::REPLACEMENT_CHARACTER);
+        return Err(ClarezaError::InvalidFormat(format!(
+            "'{}' cannot be saved as {}, save the document as UTF-8 instead",
+            unmappable,
+            encoding.name()
+        )));
+    }
+    bytes.extend_from_slice(&encoded);
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_utf8_and_legacy_text() {
+        let decoded = decode("coração".as_bytes());
+        assert_eq!(decoded.encoding, UTF_8);
+        assert_eq!(decoded.content, "coração");
+        assert!(!decoded.bom);
+
+        // "coração" in Windows-1252
+        let decoded = decode(b"cora\xE7\xE3o");
+        assert_eq!(decoded.encoding, WINDOWS_1252);
+        assert_eq!(decoded.content, "coração");
+    }
+
+    #[test]
+    fn bom_wins() {
+        let decoded = decode(b"\xEF\xBB\xBFol\xC3\xA1");
+        assert_eq!((decoded.encoding, decoded.bom), (UTF_8, true));
+        assert_eq!(decoded.content, "olá");
+
+        let decoded = decode(b"\xFF\xFEo\x00l\x00\xE1\x00");
+        assert_eq!((decoded.encoding, decoded.bom), (UTF_16LE, true));
+        assert_eq!(decoded.content, "olá");
+    }
+
+    #[test]
+    fn round_trips_every_supported_encoding() {
+        let text = "Ação, não é? «citação» — fim";
+        for label in ["utf-8", "windows-1252", "latin1", "utf-16le", "utf-16be"] {
+            let encoding = encoding_for_label(label).unwrap();
+            let bom = encoding == UTF_16LE || encoding == UTF_16BE;
+            let bytes = encode(text, encoding, bom).unwrap();
+            let decoded = decode(&bytes);
+            assert_eq!(decoded.content, text, "{}", label);
+            assert_eq!(decoded.encoding, encoding, "{}", label);
+        }
+    }
+
+    #[test]
+    fn remembers_the_encoding_a_file_was_opened_with() {
+        let path = Path::new("/nonexistent/clareza-test/legacy.txt");
+        assert_eq!(remembered_format(path).encoding, UTF_8);
+        remember_format(
+            path,
+            StoredFormat {
+                encoding: WINDOWS_1252,
+                ..Default::default()
+            },
+        );
+        assert_eq!(remembered_format(path).encoding, WINDOWS_1252);
+    }
+
+    #[test]
+    fn refuses_characters_the_encoding_lacks() {
+        let error = encode("emoji 😀", WINDOWS_1252, false).unwrap_err();
+        assert!(error.to_string().contains('😀'));
+        assert!(encoding_for_label("klingon").is_err());
+    }
+
+    #[test]
+    fn detects_and_restores_line_endings() {
+        assert_eq!(LineEnding::detect("sem quebra"), None);
+        assert_eq!(LineEnding::detect("a\r\nb\r\nc\n"), Some(LineEnding::CrLf));
+        assert_eq!(LineEnding::detect("a\nb\n"), Some(LineEnding::Lf));
+        assert_eq!(LineEnding::detect("a\rb\r"), Some(LineEnding::Cr));
+
+        let original = "linha 1\r\nlinha 2\r\n";
+        let normalized = normalize_line_endings(original);
+        assert_eq!(normalized, "linha 1\nlinha 2\n");
+        assert_eq!(LineEnding::CrLf.apply(&normalized), original);
+    }
+}
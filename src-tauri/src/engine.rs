@@ -0,0 +1,268 @@
+// src-tauri/src/engine.rs
+
+use async_trait::async_trait;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tauri::{AppHandle, Manager};
+use tokio::sync::RwLock;
+
+use crate::errors::{AiError, ClarezaError};
+use crate::gemini::{list_gemini_models, GeminiCliEngine};
+use crate::jobs::JobReporter;
+use crate::openai_compat::OpenAiCompatibleEngine;
+use crate::utils::FileUtils;
+
+pub const DEFAULT_GEMINI_MODEL: &str = "gemini-2.5-flash";
+const SETTINGS_FILE: &str = "engine_settings.json";
+
+/// A single prompt run handed to a text engine.
+#[derive(Debug, Clone)]
+pub struct EngineRequest {
+    pub prompt: String,
+    pub model: String,
+    pub timeout_secs: u64,
+    /// Whether the model may be given tools that read the open documents
+    /// (Clareza's MCP server). Off for redacted requests, since those tools
+    /// return the unmasked text.
+    pub document_tools: bool,
+}
+
+/// Backend capable of turning a prompt into text.
+///
+/// Implementations stream partial output through the job's reporter while
+/// running and return the accumulated response once the model is done.
+#[async_trait]
+pub trait TextEngine: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    async fn generate(&self, request: &EngineRequest, reporter: JobReporter)
+        -> Result<String, AiError>;
+}
+
+/// Which engine handles AI requests. Serialized as `{ "kind": "gemini-cli" }`
+/// or `{ "kind": "openai-compatible", "base_url": "...", "api_key": null }`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum ProviderConfig {
+    #[default]
+    GeminiCli,
+    /// Any server speaking the OpenAI chat completions API, e.g. Ollama at
+    /// `http://localhost:11434/v1`.
+    OpenAiCompatible {
+        base_url: String,
+        api_key: Option<String>,
+    },
+}
+
+impl ProviderConfig {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProviderConfig::GeminiCli => "gemini-cli",
+            ProviderConfig::OpenAiCompatible { .. } => "openai-compatible",
+        }
+    }
+
+    pub fn build(&self) -> Result<Box<dyn TextEngine>, AiError> {
+        match self {
+            ProviderConfig::GeminiCli => Ok(Box::new(GeminiCliEngine::locate()?)),
+            ProviderConfig::OpenAiCompatible { base_url, api_key } => Ok(Box::new(
+                OpenAiCompatibleEngine::new(base_url, api_key.clone())?,
+            )),
+        }
+    }
+
+    /// Models the provider currently serves.
+    pub async fn list_models(&self) -> Result<Vec<String>, AiError> {
+        match self {
+            ProviderConfig::GeminiCli => list_gemini_models().await,
+            ProviderConfig::OpenAiCompatible { base_url, api_key } => {
+                OpenAiCompatibleEngine::new(base_url, api_key.clone())?
+                    .list_models()
+                    .await
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct EngineState {
+    pub provider: Arc<RwLock<ProviderConfig>>,
+    pub current_model: Arc<RwLock<String>>,
+    /// Model overrides by tool id, e.g. a lighter model for grammar checks.
+    pub tool_models: Arc<RwLock<HashMap<String, String>>>,
+}
+
+impl EngineState {
+    /// The model a job of `tool_id` runs with.
+    pub async fn model_for_tool(&self, tool_id: Option<&str>) -> String {
+        if let Some(id) = tool_id {
+            if let Some(model) = self.tool_models.read().await.get(id) {
+                return model.clone();
+            }
+        }
+        self.current_model.read().await.clone()
+    }
+}
+
+pub static ENGINE_STATE: Lazy<EngineState> = Lazy::new(|| EngineState {
+    provider: Arc::new(RwLock::new(ProviderConfig::default())),
+    current_model: Arc::new(RwLock::new(DEFAULT_GEMINI_MODEL.to_string())),
+    tool_models: Arc::new(RwLock::new(HashMap::new())),
+});
+
+/// Engine choices persisted in the app config dir across restarts.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct EngineSettings {
+    provider: ProviderConfig,
+    default_model: Option<String>,
+    tool_models: HashMap<String, String>,
+}
+
+fn settings_path(app_handle: &AppHandle) -> Result<PathBuf, ClarezaError> {
+    app_handle
+        .path()
+        .app_config_dir()
+        .map(|dir| dir.join(SETTINGS_FILE))
+        .map_err(|e| ClarezaError::Path(format!("Cannot determine config directory: {}", e)))
+}
+
+/// Restore the saved provider, default model and tool overrides. Called once
+/// at startup; a missing or unreadable file leaves the defaults in place.
+pub async fn load_engine_settings(app_handle: &AppHandle) {
+    let path = match settings_path(app_handle) {
+        Ok(path) if path.exists() => path,
+        Ok(_) => return,
+        Err(e) => {
+            println!("[ENGINE] {}", e);
+            return;
+        }
+    };
+    let settings = match FileUtils::read_with_encoding(&path).await.and_then(|content| {
+        serde_json::from_str::<EngineSettings>(&content).map_err(ClarezaError::from)
+    }) {
+        Ok(settings) => settings,
+        Err(e) => {
+            println!("[ENGINE] Ignoring unreadable settings {}: {}", path.display(), e);
+            return;
+        }
+    };
+
+    println!(
+        "[ENGINE] Restored provider {} with model {:?}",
+        settings.provider.name(),
+        settings.default_model
+    );
+    *ENGINE_STATE.provider.write().await = settings.provider;
+    if let Some(model) = settings.default_model {
+        *ENGINE_STATE.current_model.write().await = model;
+    }
+    *ENGINE_STATE.tool_models.write().await = settings.tool_models;
+}
+
+/// Write the current engine choices to the settings file.
+pub async fn save_engine_settings(app_handle: &AppHandle) -> Result<(), ClarezaError> {
+    let settings = EngineSettings {
+        provider: ENGINE_STATE.provider.read().await.clone(),
+        default_model: Some(ENGINE_STATE.current_model.read().await.clone()),
+        tool_models: ENGINE_STATE.tool_models.read().await.clone(),
+    };
+    let path = settings_path(app_handle)?;
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    FileUtils::atomic_write(&path, &serde_json::to_string_pretty(&settings)?).await
+}
+
+/// Check `model` against the provider's model list. When the list cannot be
+/// fetched (offline, provider down) any non-empty name is accepted.
+pub async fn validate_model(provider: &ProviderConfig, model: &str) -> Result<(), String> {
+    if model.trim().is_empty() {
+        return Err("Model name cannot be empty".to_string());
+    }
+    match provider.list_models().await {
+        Ok(models) if !models.is_empty() && !models.iter().any(|m| m == model) => Err(format!(
+            "Invalid model '{}'. Valid models: {}",
+            model,
+            models.join(", ")
+        )),
+        Ok(_) => Ok(()),
+        Err(e) => {
+            println!("[ENGINE] Could not list models, accepting '{}': {}", model, e);
+            Ok(())
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn set_ai_provider(
+    app_handle: AppHandle,
+    provider: ProviderConfig,
+    model: Option<String>,
+) -> Result<(), String> {
+    let model = match (&provider, model) {
+        (_, Some(m)) if !m.trim().is_empty() => m,
+        (ProviderConfig::GeminiCli, _) => DEFAULT_GEMINI_MODEL.to_string(),
+        (ProviderConfig::OpenAiCompatible { .. }, _) => {
+            return Err("A model name is required for OpenAI-compatible providers".to_string())
+        }
+    };
+
+    // Fail early on an unusable configuration instead of at the first prompt
+    provider.build().map_err(|e| e.to_string())?;
+
+    println!("[ENGINE] Provider changed to {:?} with model {}", provider, model);
+    // Overrides name models of the previous provider
+    if ENGINE_STATE.provider.read().await.name() != provider.name() {
+        ENGINE_STATE.tool_models.write().await.clear();
+    }
+    *ENGINE_STATE.provider.write().await = provider;
+    *ENGINE_STATE.current_model.write().await = model;
+    save_engine_settings(&app_handle)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_ai_provider() -> Result<ProviderConfig, String> {
+    Ok(ENGINE_STATE.provider.read().await.clone())
+}
+
+/// Models offered by the active provider.
+#[tauri::command]
+pub async fn list_available_models() -> Result<Vec<String>, String> {
+    let provider = ENGINE_STATE.provider.read().await.clone();
+    provider.list_models().await.map_err(|e| e.to_string())
+}
+
+/// Set the model used for one tool, or go back to the default with `None`.
+#[tauri::command]
+pub async fn set_tool_model(
+    app_handle: AppHandle,
+    tool_id: String,
+    model: Option<String>,
+) -> Result<(), String> {
+    match model {
+        Some(model) => {
+            let provider = ENGINE_STATE.provider.read().await.clone();
+            validate_model(&provider, &model).await?;
+            println!("[ENGINE] Tool {} now uses {}", tool_id, model);
+            ENGINE_STATE.tool_models.write().await.insert(tool_id, model);
+        }
+        None => {
+            println!("[ENGINE] Tool {} now uses the default model", tool_id);
+            ENGINE_STATE.tool_models.write().await.remove(&tool_id);
+        }
+    }
+    save_engine_settings(&app_handle)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_tool_models() -> Result<HashMap<String, String>, String> {
+    Ok(ENGINE_STATE.tool_models.read().await.clone())
+}
@@ -1,4 +1,6 @@
 // src-tauri/src/errors.rs
+use once_cell::sync::Lazy;
+use regex::Regex;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -26,6 +28,195 @@ pub enum ClarezaError {
     
     #[error("Export error: {0}")]
     Export(String),
+
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    #[error("Locked: {0}")]
+    Locked(String),
+
+    #[error("AI error: {0}")]
+    Ai(#[from] AiError),
+}
+
+/// Why an AI request failed, classified from engine output so the UI can
+/// suggest a fix.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum AiError {
+    #[error("Not authenticated: {0}")]
+    NotAuthenticated(String),
+
+    #[error("Rate limit exceeded: {0}")]
+    RateLimited(String),
+
+    #[error("Quota exhausted: {0}")]
+    QuotaExceeded(String),
+
+    #[error("Model unavailable: {0}")]
+    ModelUnavailable(String),
+
+    #[error("Network unreachable: {0}")]
+    NetworkUnreachable(String),
+
+    #[error("Prompt too large: {0}")]
+    PromptTooLarge(String),
+
+    #[error("Timed out after {0} seconds")]
+    Timeout(u64),
+
+    #[error("AI executable not found: {0}")]
+    BinaryMissing(String),
+
+    #[error("{0}")]
+    Other(String),
+}
+
+fn pattern(source: &str) -> Regex {
+    Regex::new(source).expect("valid classification pattern")
+}
+
+macro_rules! status {
+    ($code:literal) => {
+        concat!(r"(?:^|[^\w:.])", $code, r"(?:$|[^\w:.]|:\D|:$)")
+    };
+}
+
+// Matched against lowercased text. Status codes need a word boundary that
+// also excludes `:` and `.`, or positions in stack frames would count.
+static RATE_LIMITED: Lazy<Regex> = Lazy::new(|| {
+    pattern(concat!(
+        r"\b(?:quota|rate ?limit(?:ed)?|resource_exhausted|too many requests)\b|",
+        status!("429")
+    ))
+});
+/// A quota that stays exhausted for hours (daily limits, billing), unlike
+/// per-minute limits which the same wording is also used for.
+static QUOTA_EXCEEDED: Lazy<Regex> = Lazy::new(|| {
+    pattern(concat!(
+        r"\b(?:quota exceeded|exceeded (?:your )?(?:current )?quota|insufficient_quota",
+        r"|quota (?:has been )?exhausted|daily (?:quota|limit)|per day|billing)\b"
+    ))
+});
+static SHORT_TERM_LIMIT: Lazy<Regex> = Lazy::new(|| {
+    pattern(r"\b(?:per minute|per second|retry (?:in|after)|rate ?limit(?:ed)?)\b")
+});
+static NOT_AUTHENTICATED: Lazy<Regex> = Lazy::new(|| {
+    pattern(concat!(
+        r"\b(?:unauthenticated|not authenticated|unauthorized|login required|permission_denied",
+        r"|authentication (?:failed|required|error)",
+        r"|invalid api key|api key not valid|gemini_api_key",
+        r"|(?:invalid|missing|expired|default) credentials|credentials (?:not found|expired))\b|",
+        status!("401")
+    ))
+});
+static PROMPT_TOO_LARGE: Lazy<Regex> = Lazy::new(|| {
+    pattern(concat!(
+        r"\b(?:too large|token limit|context length|context window",
+        r"|exceeds the maximum|maximum context)\b|",
+        status!("413")
+    ))
+});
+static MODEL_UNAVAILABLE: Lazy<Regex> = Lazy::new(|| {
+    pattern(concat!(
+        r"(?s)\bmodels?\b.*(?:\b(?:not found|not supported|unavailable|does not exist)\b|",
+        status!("404"),
+        ")"
+    ))
+});
+static NETWORK_UNREACHABLE: Lazy<Regex> = Lazy::new(|| {
+    pattern(concat!(
+        r"\b(?:enotfound|econnrefused|econnreset|etimedout|eai_again|getaddrinfo",
+        r"|fetch failed|network error|network is unreachable|connection refused|dns)\b"
+    ))
+});
+/// Lines on stderr worth classifying; the CLI also prints progress, warnings
+/// and stack frames there.
+static ERROR_LINE: Lazy<Regex> = Lazy::new(|| {
+    pattern(concat!(
+        r"\b(?:error|failed|failure|exception|denied|unauthori[sz]ed|unauthenticated",
+        r"|invalid|quota|exceeded|refused|exhausted)\b"
+    ))
+});
+
+impl AiError {
+    /// Classify a raw error message from a stream-json `error` object, an
+    /// HTTP response body or the error lines of stderr (see `is_error_line`).
+    pub fn classify(detail: &str) -> AiError {
+        let lower = detail.to_lowercase();
+        let detail = detail.trim().to_string();
+
+        if QUOTA_EXCEEDED.is_match(&lower) && !SHORT_TERM_LIMIT.is_match(&lower) {
+            AiError::QuotaExceeded(detail)
+        } else if RATE_LIMITED.is_match(&lower) {
+            AiError::RateLimited(detail)
+        } else if NOT_AUTHENTICATED.is_match(&lower) {
+            AiError::NotAuthenticated(detail)
+        } else if PROMPT_TOO_LARGE.is_match(&lower) {
+            AiError::PromptTooLarge(detail)
+        } else if MODEL_UNAVAILABLE.is_match(&lower) {
+            AiError::ModelUnavailable(detail)
+        } else if NETWORK_UNREACHABLE.is_match(&lower) {
+            AiError::NetworkUnreachable(detail)
+        } else {
+            AiError::Other(detail)
+        }
+    }
+
+    /// Whether a stderr line reports an error, as opposed to progress output
+    /// or a stack frame (`    at fn (file.js:429:13)`).
+    pub fn is_error_line(line: &str) -> bool {
+        let line = line.trim_start();
+        !line.starts_with("at ") && ERROR_LINE.is_match(&line.to_lowercase())
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AiError::NotAuthenticated(_) => "not_authenticated",
+            AiError::RateLimited(_) => "rate_limited",
+            AiError::QuotaExceeded(_) => "quota_exceeded",
+            AiError::ModelUnavailable(_) => "model_unavailable",
+            AiError::NetworkUnreachable(_) => "network_unreachable",
+            AiError::PromptTooLarge(_) => "prompt_too_large",
+            AiError::Timeout(_) => "timeout",
+            AiError::BinaryMissing(_) => "binary_missing",
+            AiError::Other(_) => "other",
+        }
+    }
+
+    /// Whether trying again later can succeed without user action.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            AiError::RateLimited(_) | AiError::NetworkUnreachable(_) | AiError::Timeout(_)
+        )
+    }
+
+    /// What the user can do about it, shown in the UI.
+    pub fn hint(&self) -> &'static str {
+        match self {
+            AiError::NotAuthenticated(_) => {
+                "Execute `gemini` em um terminal e faça login com sua conta Google."
+            }
+            AiError::RateLimited(_) => {
+                "Limite de requisições atingido. Aguarde alguns instantes e tente novamente."
+            }
+            AiError::QuotaExceeded(_) => {
+                "Cota do modelo esgotada. Use um modelo mais leve ou tente novamente mais tarde."
+            }
+            AiError::ModelUnavailable(_) => {
+                "O modelo selecionado não está disponível. Escolha outro modelo."
+            }
+            AiError::NetworkUnreachable(_) => {
+                "Sem conexão com o serviço de IA. Verifique a internet ou se o servidor local está rodando."
+            }
+            AiError::PromptTooLarge(_) => {
+                "O texto é grande demais para uma requisição. Selecione um trecho menor ou reduza o tamanho das partes."
+            }
+            AiError::Timeout(_) => "A IA demorou demais para responder. Tente novamente com um trecho menor.",
+            AiError::BinaryMissing(_) => "Gemini CLI não encontrado. Instale-o pela tela de dependências.",
+            AiError::Other(_) => "Veja o terminal para mais detalhes.",
+        }
+    }
 }
 
 impl serde::Serialize for ClarezaError {
@@ -36,3 +227,87 @@ impl serde::Serialize for ClarezaError {
         serializer.serialize_str(self.to_string().as_ref())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_status_codes_as_whole_numbers() {
+        assert!(matches!(
+            AiError::classify("Request failed with status 429"),
+            AiError::RateLimited(_)
+        ));
+        assert!(matches!(
+            AiError::classify("HTTP 401: Unauthorized"),
+            AiError::NotAuthenticated(_)
+        ));
+        assert!(matches!(
+            AiError::classify("TypeError: x is undefined\n    at run (file:///cli.js:429:13)"),
+            AiError::Other(_)
+        ));
+        assert!(matches!(
+            AiError::classify("token 14290 rejected"),
+            AiError::Other(_)
+        ));
+    }
+
+    #[test]
+    fn classifies_known_messages() {
+        assert!(matches!(
+            AiError::classify("RESOURCE_EXHAUSTED: Quota exceeded"),
+            AiError::QuotaExceeded(_)
+        ));
+        assert!(matches!(
+            AiError::classify("Could not load the default credentials"),
+            AiError::NotAuthenticated(_)
+        ));
+        assert!(matches!(
+            AiError::classify("Saved credentials to ~/.gemini"),
+            AiError::Other(_)
+        ));
+        assert!(matches!(
+            AiError::classify("Model gemini-9 not found"),
+            AiError::ModelUnavailable(_)
+        ));
+        assert!(matches!(
+            AiError::classify("getaddrinfo ENOTFOUND example.com"),
+            AiError::NetworkUnreachable(_)
+        ));
+        assert!(matches!(
+            AiError::classify("Input exceeds the maximum number of tokens"),
+            AiError::PromptTooLarge(_)
+        ));
+    }
+
+    #[test]
+    fn separates_exhausted_quota_from_rate_limits() {
+        assert!(matches!(
+            AiError::classify(
+                "Quota exceeded for quota metric 'Gemini 2.5 Pro Requests' and limit \
+                 'Gemini 2.5 Pro Requests per day per user'"
+            ),
+            AiError::QuotaExceeded(_)
+        ));
+        assert!(matches!(
+            AiError::classify(
+                "Quota exceeded for quota metric 'Gemini 2.5 Pro Requests' and limit \
+                 'Gemini 2.5 Pro Requests per minute per user'"
+            ),
+            AiError::RateLimited(_)
+        ));
+        assert!(matches!(
+            AiError::classify("429 Too Many Requests"),
+            AiError::RateLimited(_)
+        ));
+        assert!(AiError::RateLimited(String::new()).is_transient());
+        assert!(!AiError::QuotaExceeded(String::new()).is_transient());
+    }
+
+    #[test]
+    fn recognises_error_lines() {
+        assert!(AiError::is_error_line("Error: request failed"));
+        assert!(!AiError::is_error_line("    at processTicksAndRejections (node:internal:95:5)"));
+        assert!(!AiError::is_error_line("Loaded cached credentials."));
+    }
+}
@@ -1,37 +1,46 @@
 // src-tauri/src/gemini.rs
 
-use once_cell::sync::Lazy;
+use async_trait::async_trait;
+use futures_util::{stream, StreamExt, TryStreamExt};
+use serde::Deserialize;
 use std::env;
+use std::path::Path;
 use std::sync::Arc;
 use std::time::Duration;
-use tauri::{AppHandle, Emitter, Manager};
+use tauri::ipc::Channel;
+use tauri::AppHandle;
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tokio::sync::RwLock;
 use tokio::time::timeout;
 
-#[derive(Clone, serde::Serialize)]
-struct TerminalOutput {
-    message: String,
-    stream: String,
-}
-
-#[derive(Clone, serde::Serialize)]
-struct GeminiComplete {
-    content: String,
-}
+use crate::cache::generate_cached;
+use crate::chunking::{split_markdown, stitch, Chunk, ChunkingOptions};
+use crate::diff::{diff_texts, DiffGranularity};
+use crate::engine::{
+    save_engine_settings, validate_model, EngineRequest, TextEngine, ENGINE_STATE,
+};
+use crate::errors::{AiError, ClarezaError};
+use crate::jobs::{cancel_job, spawn_job, ChunkStatus, CompletionExtras, JobEvent, JobReporter};
+use crate::mcp::{self, McpServerInfo};
+use crate::openai_compat::{request_error, status_error};
+use crate::fencing::{replace_blocks, Fence};
+use crate::pipeline::RequestPipeline;
+use crate::redaction::RedactionOptions;
+use crate::retry::RetryPolicy;
+use crate::templates::{Template, TemplateVariables, Variable};
+use crate::workspace::JobWorkspace;
 
-#[derive(Clone)]
-struct GeminiState {
-    current_model: Arc<RwLock<String>>,
+/// First `max_chars` characters of `text`, safe for multi-byte content.
+fn preview(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((idx, _)) => format!("{}...", &text[..idx]),
+        None => text.to_string(),
+    }
 }
 
-static GEMINI_STATE: Lazy<GeminiState> = Lazy::new(|| GeminiState {
-    current_model: Arc::new(RwLock::new("gemini-2.5-flash".to_string())),
-});
-
-fn find_gemini_executable() -> Result<String, String> {
+fn find_gemini_executable() -> Result<String, AiError> {
     let path_var = env::var("PATH")
-        .map_err(|_| "PATH environment variable not found".to_string())?;
+        .map_err(|_| AiError::BinaryMissing("PATH environment variable not found".to_string()))?;
     
     let separator = if cfg!(target_os = "windows") { ';' } else { ':' };
     let extensions = if cfg!(target_os = "windows") {
@@ -49,7 +58,7 @@ fn find_gemini_executable() -> Result<String, String> {
         }
     }
     
-    Err("Gemini CLI not found in PATH".to_string())
+    Err(AiError::BinaryMissing("Gemini CLI not found in PATH".to_string()))
 }
 
 fn is_batch_file(path: &str) -> bool {
@@ -57,15 +66,47 @@ fn is_batch_file(path: &str) -> bool {
     path_lower.ends_with(".cmd") || path_lower.ends_with(".bat")
 }
 
+/// Kill a process and everything it started. On Windows the CLI runs as
+/// `cmd /C gemini.cmd`, and killing `cmd.exe` alone leaves node running.
+/// Elsewhere the child is the CLI itself and `kill_on_drop` is enough.
+fn kill_process_tree(pid: u32) {
+    if cfg!(target_os = "windows") {
+        let result = std::process::Command::new("taskkill")
+            .args(["/T", "/F", "/PID", &pid.to_string()])
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn();
+        if let Err(e) = result {
+            println!("[GEMINI] Failed to kill process tree {}: {}", pid, e);
+        }
+    }
+}
+
+/// Kills the process tree when dropped before the CLI exited, i.e. when the
+/// job is cancelled or times out.
+struct ProcessTreeGuard {
+    pid: Option<u32>,
+}
+
+impl Drop for ProcessTreeGuard {
+    fn drop(&mut self) {
+        if let Some(pid) = self.pid.take() {
+            kill_process_tree(pid);
+        }
+    }
+}
+
 async fn execute_gemini_command_streaming(
     gemini_path: &str,
     prompt: &str,
     model: Option<&str>,
-    app_handle: AppHandle,
+    reporter: JobReporter,
     timeout_secs: u64,
-) -> Result<String, String> {
+    workdir: &Path,
+) -> Result<String, AiError> {
     println!("[GEMINI] Executing command with streaming (timeout: {}s)", timeout_secs);
     println!("[GEMINI] Gemini path: {}", gemini_path);
+    println!("[GEMINI] Working directory: {}", workdir.display());
     println!("[GEMINI] Prompt length: {}", prompt.len());
     
     // Build the command arguments
@@ -93,41 +134,59 @@ async fn execute_gemini_command_streaming(
     // Add all arguments
     cmd.args(&args);
     
+    // Never the user's home or project: the CLI can read files relative to its cwd
+    cmd.current_dir(workdir);
+    
+    // Kill the CLI if the job owning this future is cancelled, see also
+    // `ProcessTreeGuard`
+    cmd.kill_on_drop(true);
+    
     // Use stdin for the prompt to avoid command line length limits
     cmd.stdin(std::process::Stdio::piped());
     cmd.stdout(std::process::Stdio::piped());
     cmd.stderr(std::process::Stdio::piped());
     
     println!("[GEMINI] Spawning process with stdin...");
-    let mut child = cmd.spawn()
-        .map_err(|e| format!("Failed to spawn gemini process: {}", e))?;
+    let mut child = cmd.spawn().map_err(|e| {
+        if e.kind() == std::io::ErrorKind::NotFound {
+            AiError::BinaryMissing(format!("Failed to spawn gemini process: {}", e))
+        } else {
+            AiError::Other(format!("Failed to spawn gemini process: {}", e))
+        }
+    })?;
+    let mut tree_guard = ProcessTreeGuard { pid: child.id() };
     
     // Write prompt to stdin
     if let Some(mut stdin) = child.stdin.take() {
         println!("[GEMINI] Writing prompt to stdin ({} bytes)...", prompt.as_bytes().len());
         stdin.write_all(prompt.as_bytes()).await
-            .map_err(|e| format!("Failed to write to stdin: {}", e))?;
+            .map_err(|e| AiError::Other(format!("Failed to write to stdin: {}", e)))?;
         stdin.flush().await
-            .map_err(|e| format!("Failed to flush stdin: {}", e))?;
+            .map_err(|e| AiError::Other(format!("Failed to flush stdin: {}", e)))?;
         drop(stdin); // Close stdin to signal end of input
         println!("[GEMINI] Stdin written and closed");
     }
     
     let stdout = child.stdout.take()
-        .ok_or_else(|| "Failed to capture stdout".to_string())?;
+        .ok_or_else(|| AiError::Other("Failed to capture stdout".to_string()))?;
     
     let stderr = child.stderr.take()
-        .ok_or_else(|| "Failed to capture stderr".to_string())?;
+        .ok_or_else(|| AiError::Other("Failed to capture stderr".to_string()))?;
     
     let stdout_reader = BufReader::new(stdout);
     let stderr_reader = BufReader::new(stderr);
     
-    let app_handle_stdout = app_handle.clone();
-    let app_handle_stderr = app_handle.clone();
+    let reporter_stdout = reporter.clone();
+    let reporter_stderr = reporter.clone();
     
     let accumulated_content = Arc::new(RwLock::new(String::new()));
     let accumulated_content_clone = accumulated_content.clone();
     
+    // Stream-json errors and error lines of stderr, classified if the run fails
+    let error_details = Arc::new(RwLock::new(Vec::<String>::new()));
+    let error_details_stdout = error_details.clone();
+    let error_details_stderr = error_details.clone();
+    
     let stdout_handle = tokio::spawn(async move {
         let mut lines = stdout_reader.lines();
         while let Ok(Some(line)) = lines.next_line().await {
@@ -138,43 +197,25 @@ async fn execute_gemini_command_streaming(
                             if role == "assistant" {
                                 if let Some(content) = json.get("content").and_then(|v| v.as_str()) {
                                     accumulated_content_clone.write().await.push_str(content);
-                                    
-                                    if let Some(window) = app_handle_stdout.get_webview_window("main") {
-                                        let _ = window.emit(
-                                            "terminal-output",
-                                            TerminalOutput {
-                                                message: content.to_string(),
-                                                stream: "stdout".to_string(),
-                                            },
-                                        );
-                                    }
+                                    reporter_stdout.output(content, "stdout");
                                 }
                             }
                         }
                     }
                 }
                 
-                if let Some(error) = json.get("error").and_then(|v| v.as_str()) {
-                    if let Some(window) = app_handle_stdout.get_webview_window("main") {
-                        let _ = window.emit(
-                            "terminal-output",
-                            TerminalOutput {
-                                message: format!("Error: {}", error),
-                                stream: "stderr".to_string(),
-                            },
-                        );
-                    }
+                if let Some(error) = json.get("error") {
+                    // Either a plain string or an object with a `message`
+                    let message = error
+                        .as_str()
+                        .or_else(|| error.get("message").and_then(|v| v.as_str()))
+                        .map(str::to_string)
+                        .unwrap_or_else(|| error.to_string());
+                    reporter_stdout.output(format!("Error: {}", message), "stderr");
+                    error_details_stdout.write().await.push(message);
                 }
             } else {
-                if let Some(window) = app_handle_stdout.get_webview_window("main") {
-                    let _ = window.emit(
-                        "terminal-output",
-                        TerminalOutput {
-                            message: line,
-                            stream: "stdout".to_string(),
-                        },
-                    );
-                }
+                reporter_stdout.output(line, "stdout");
             }
         }
     });
@@ -182,170 +223,487 @@ async fn execute_gemini_command_streaming(
     let stderr_handle = tokio::spawn(async move {
         let mut lines = stderr_reader.lines();
         while let Ok(Some(line)) = lines.next_line().await {
-            if let Some(window) = app_handle_stderr.get_webview_window("main") {
-                let _ = window.emit(
-                    "terminal-output",
-                    TerminalOutput {
-                        message: line,
-                        stream: "stderr".to_string(),
-                    },
-                );
+            if AiError::is_error_line(&line) {
+                error_details_stderr.write().await.push(line.clone());
             }
+            reporter_stderr.output(line, "stderr");
         }
     });
     
     let wait_result = timeout(Duration::from_secs(timeout_secs), child.wait()).await;
     
-    tokio::join!(stdout_handle, stderr_handle);
+    if wait_result.is_err() {
+        // Kill before joining the readers, they only finish once the pipes close
+        if let Some(pid) = tree_guard.pid.take() {
+            kill_process_tree(pid);
+        }
+        let _ = child.kill().await;
+    }
+    tree_guard.pid = None;
+    let _ = tokio::join!(stdout_handle, stderr_handle);
     
     match wait_result {
         Ok(Ok(status)) => {
+            let content = accumulated_content.read().await.clone();
+            let details = error_details.read().await.join("\n");
+            
             if !status.success() {
-                return Err(format!("Gemini CLI exited with status: {}", status));
+                let detail = if details.is_empty() {
+                    format!("Gemini CLI exited with status: {}", status)
+                } else {
+                    details
+                };
+                return Err(AiError::classify(&detail));
+            }
+            if content.is_empty() && !details.is_empty() {
+                return Err(AiError::classify(&details));
             }
             
-            let content = accumulated_content.read().await.clone();
             println!("[GEMINI] Process completed successfully, accumulated {} chars", content.len());
             Ok(content)
         }
-        Ok(Err(e)) => Err(format!("Failed to wait for process: {}", e)),
-        Err(_) => {
-            let _ = child.kill().await;
-            Err(format!("Command timed out after {} seconds", timeout_secs))
+        Ok(Err(e)) => Err(AiError::Other(format!("Failed to wait for process: {}", e))),
+        Err(_) => Err(AiError::Timeout(timeout_secs)),
+    }
+}
+
+/// Text engine backed by the Gemini CLI binary found in PATH.
+pub struct GeminiCliEngine {
+    executable: String,
+}
+
+impl GeminiCliEngine {
+    pub fn locate() -> Result<Self, AiError> {
+        Ok(Self {
+            executable: find_gemini_executable()?,
+        })
+    }
+}
+
+#[async_trait]
+impl TextEngine for GeminiCliEngine {
+    fn name(&self) -> &'static str {
+        "gemini-cli"
+    }
+
+    /// Fenced documents are written to a private workspace and referenced
+    /// as `@file` instead of being inlined in stdin.
+    async fn generate(
+        &self,
+        request: &EngineRequest,
+        reporter: JobReporter,
+    ) -> Result<String, AiError> {
+        let workspace = JobWorkspace::create(reporter.job_id()).await?;
+        let mut files = Vec::new();
+        let prompt = replace_blocks(&request.prompt, |label, block| {
+            let name = workspace_file_name(files.len(), label);
+            let reference = format!("@{}", name);
+            files.push((name, block.to_string()));
+            reference
+        });
+        for (name, content) in &files {
+            workspace.write_file(name, content).await?;
+        }
+        println!("[GEMINI] {} documents passed as files", files.len());
+        if let Some(server) = mcp::server_info().await.filter(|_| request.document_tools) {
+            write_mcp_settings(&workspace, &server, reporter.job_id()).await?;
         }
+
+        execute_gemini_command_streaming(
+            &self.executable,
+            &prompt,
+            Some(&request.model),
+            reporter,
+            request.timeout_secs,
+            workspace.path(),
+        )
+        .await
     }
 }
 
+/// Register Clareza's MCP server in the workspace settings of the CLI, so
+/// the model can read the open documents and propose edits. Trusted because
+/// its tools only read or record proposals the user still has to accept.
+async fn write_mcp_settings(
+    workspace: &JobWorkspace,
+    server: &McpServerInfo,
+    job_id: &str,
+) -> Result<(), AiError> {
+    let dir = workspace.path().join(".gemini");
+    let settings = serde_json::json!({
+        "mcpServers": {
+            mcp::MCP_SERVER_NAME: {
+                "httpUrl": server.url,
+                "headers": {
+                    "Authorization": format!("Bearer {}", server.token),
+                    mcp::JOB_HEADER: job_id,
+                },
+                "trust": true,
+            }
+        }
+    });
+    tokio::fs::create_dir_all(&dir)
+        .await
+        .map_err(|e| AiError::Other(format!("Failed to write CLI settings: {}", e)))?;
+    tokio::fs::write(dir.join("settings.json"), settings.to_string())
+        .await
+        .map_err(|e| AiError::Other(format!("Failed to write CLI settings: {}", e)))
+}
+
+/// `01-documento.md` for the first block labelled "documento". The fenced
+/// markers stay inside the file, so the preamble still applies to it.
+fn workspace_file_name(index: usize, label: &str) -> String {
+    let slug: String = label
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '-' })
+        .collect();
+    format!("{:02}-{}.md", index + 1, slug.trim_matches('-'))
+}
+
+/// Models offered when the API cannot be queried (the CLI logged in with a
+/// Google account has no API key to list them with).
+const KNOWN_GEMINI_MODELS: &[&str] = &[
+    "gemini-2.5-flash",
+    "gemini-2.5-flash-lite",
+    "gemini-2.5-pro",
+];
+
+const GEMINI_API_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+
+/// Gemini models that can generate content, queried from the API when an
+/// API key is configured for the CLI.
+pub(crate) async fn list_gemini_models() -> Result<Vec<String>, AiError> {
+    let api_key = env::var("GEMINI_API_KEY")
+        .or_else(|_| env::var("GOOGLE_API_KEY"))
+        .ok()
+        .filter(|key| !key.trim().is_empty());
+    let Some(api_key) = api_key else {
+        return Ok(KNOWN_GEMINI_MODELS.iter().map(|m| m.to_string()).collect());
+    };
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(15))
+        .build()
+        .map_err(|e| AiError::Other(format!("Failed to create HTTP client: {}", e)))?;
+    let response = client
+        .get(format!("{}/models", GEMINI_API_URL))
+        // A header, unlike `?key=` it cannot end up in logged URLs
+        .header("x-goog-api-key", api_key.as_str())
+        .query(&[("pageSize", "1000")])
+        .send()
+        .await
+        .map_err(|e| request_error(GEMINI_API_URL, e))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let text = response.text().await.unwrap_or_default();
+        return Err(status_error(status, &text));
+    }
+
+    let json: serde_json::Value = response
+        .json()
+        .await
+        .map_err(|e| AiError::Other(format!("Invalid model list: {}", e)))?;
+    let mut models: Vec<String> = json["models"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|model| {
+            model["supportedGenerationMethods"]
+                .as_array()
+                .is_some_and(|methods| methods.iter().any(|m| m == "generateContent"))
+        })
+        .filter_map(|model| model["name"].as_str())
+        .map(|name| name.trim_start_matches("models/").to_string())
+        .filter(|name| name.starts_with("gemini"))
+        .collect();
+    models.sort();
+    println!("[GEMINI] Discovered {} models", models.len());
+    Ok(models)
+}
+
+/// Resolve the active engine and model and create the reporter for a new job.
+pub(crate) async fn prepare_job(
+    app_handle: AppHandle,
+    tool_id: Option<String>,
+    on_event: Option<Channel<JobEvent>>,
+) -> Result<(Box<dyn TextEngine>, String, JobReporter), ClarezaError> {
+    let provider = ENGINE_STATE.provider.read().await.clone();
+    let model = ENGINE_STATE.model_for_tool(tool_id.as_deref()).await;
+    let reporter = JobReporter::new(app_handle, tool_id, provider.name(), &model, on_event);
+    
+    // Report setup failures (e.g. missing CLI) as typed events too
+    match provider.build() {
+        Ok(engine) => Ok((engine, model, reporter)),
+        Err(e) => {
+            reporter.failed(&e);
+            Err(e.into())
+        }
+    }
+}
+
+/// Optional knobs for `send_prompt_to_gemini`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct PromptOptions {
+    /// Attach a diff against `file_content` to the completion event.
+    pub diff_granularity: Option<DiffGranularity>,
+    /// Overrides the chunk budget used for documents too long for one request.
+    pub chunking: Option<ChunkingOptions>,
+    /// Overrides how transient failures are retried.
+    pub retry: Option<RetryPolicy>,
+    /// Values for `{{...}}` placeholders in the prompt. `document` is always
+    /// filled from `file_content` (or the current chunk).
+    pub variables: Option<TemplateVariables>,
+    /// Ignore cached answers and always call the engine.
+    pub bypass_cache: bool,
+    /// Mask sensitive data before it leaves the machine.
+    pub redaction: Option<RedactionOptions>,
+}
+
+/// Render the user's prompt and substitute the document into it.
+///
+/// Templates that place `{{document}}` themselves are used as rendered;
+/// otherwise the legacy `@file_reference` marker is replaced, or the document
+/// is appended. The document and selection are fenced off from the
+/// instruction, see `fencing::Fence`.
+pub(crate) fn build_prompt(
+    template: &Template,
+    variables: &TemplateVariables,
+    content: Option<&str>,
+) -> String {
+    if content.is_none() && variables.selection.is_none() {
+        return template.render(variables);
+    }
+
+    let fence = Fence::for_contents(&[
+        content.unwrap_or_default(),
+        variables.selection.as_deref().unwrap_or_default(),
+    ]);
+    let mut variables = variables.clone();
+    variables.document = content.map(|content| fence.wrap("documento", content));
+    variables.selection = variables
+        .selection
+        .map(|selection| fence.wrap("trecho selecionado", &selection));
+    let prompt = template.render(&variables);
+
+    let prompt = match content {
+        _ if template.uses(Variable::Document) => prompt,
+        Some(content) if prompt.contains("@file_reference") => prompt.replace(
+            "@file_reference",
+            &format!("\n\nConteúdo do arquivo:\n{}\n", fence.wrap("documento", content)),
+        ),
+        Some(content) => format!(
+            "{}\n\nConteúdo do arquivo atual:\n{}",
+            prompt,
+            fence.wrap("documento", content)
+        ),
+        None => prompt,
+    };
+    format!("{}{}", fence.preamble(), prompt)
+}
+
+fn build_chunk_prompt(
+    template: &Template,
+    variables: &TemplateVariables,
+    chunk: &Chunk,
+    total: usize,
+) -> String {
+    format!(
+        "{}\n\nObservação: este é o trecho {} de {} de um documento maior. \
+Responda apenas sobre este trecho, sem introduções nem comentários sobre as outras partes.",
+        build_prompt(template, variables, Some(&chunk.text)),
+        chunk.index + 1,
+        total
+    )
+}
+
+/// Run the prompt over the document, splitting it into chunks processed
+/// concurrently when it does not fit in a single request.
+async fn generate_for_document(
+    engine: &dyn TextEngine,
+    template: &Template,
+    variables: &TemplateVariables,
+    content: Option<&str>,
+    model: &str,
+    options: &PromptOptions,
+    reporter: &JobReporter,
+) -> Result<String, AiError> {
+    let chunking = options.chunking.clone().unwrap_or_default();
+    let retry = &options.retry.clone().unwrap_or_default();
+    let chunks = content
+        .map(|c| split_markdown(c, chunking.max_tokens))
+        .unwrap_or_default();
+
+    if chunks.len() <= 1 {
+        let full_prompt = build_prompt(template, variables, content);
+        println!("[GEMINI] Full prompt length: {}", full_prompt.len());
+        println!("[GEMINI] Full prompt preview: {}", preview(&full_prompt, 200));
+        let request = EngineRequest {
+            prompt: full_prompt,
+            model: model.to_string(),
+            timeout_secs: 120,
+            document_tools: options.redaction.is_none(),
+        };
+        return generate_cached(engine, &request, retry, options.bypass_cache, reporter).await;
+    }
+
+    let total = chunks.len();
+    println!("[GEMINI] Document split into {} chunks", total);
+    reporter.output(format!("📄 Documento dividido em {} partes", total), "system");
+
+    // Collected up front: a lazy `map` closure here trips the `Send` check on
+    // the spawned job future
+    let requests: Vec<_> = chunks
+        .iter()
+        .map(|chunk| {
+            let request = EngineRequest {
+                prompt: build_chunk_prompt(template, variables, chunk, total),
+                model: model.to_string(),
+                timeout_secs: 120,
+                document_tools: options.redaction.is_none(),
+            };
+            async move {
+                reporter.chunk_progress(chunk.index, total, ChunkStatus::Started);
+                let result =
+                    generate_cached(engine, &request, retry, options.bypass_cache, reporter).await;
+                let status = if result.is_ok() {
+                    ChunkStatus::Completed
+                } else {
+                    ChunkStatus::Failed
+                };
+                reporter.chunk_progress(chunk.index, total, status);
+                if let Err(e) = &result {
+                    println!("[GEMINI] Chunk {}/{} failed: {}", chunk.index + 1, total, e);
+                }
+                result
+            }
+        })
+        .collect();
+
+    let outputs: Vec<String> = stream::iter(requests)
+        .buffered(chunking.max_concurrency.max(1))
+        .try_collect()
+        .await?;
+
+    Ok(stitch(&chunks, &outputs))
+}
+
 #[tauri::command]
 pub async fn send_prompt_to_gemini(
     app_handle: AppHandle,
     prompt: String,
     file_content: Option<String>,
-) -> Result<(), String> {
+    tool_id: Option<String>,
+    options: Option<PromptOptions>,
+    on_event: Option<Channel<JobEvent>>,
+) -> Result<String, ClarezaError> {
     println!("[GEMINI] Received prompt request");
     println!("[GEMINI] Prompt length: {}", prompt.len());
-    
-    let gemini_path = find_gemini_executable()?;
-    
-    let full_prompt = if let Some(content) = &file_content {
-        println!("[GEMINI] File content length: {}", content.len());
-        if prompt.contains("@file_reference") {
-            prompt.replace("@file_reference", &format!("\n\nConteúdo do arquivo:\n```\n{}\n```\n", content))
-        } else {
-            format!("{}\n\nConteúdo do arquivo atual:\n```\n{}\n```", prompt, content)
-        }
-    } else {
-        println!("[GEMINI] No file content provided");
-        prompt.clone()
-    };
-    
-    println!("[GEMINI] Full prompt length: {}", full_prompt.len());
-    println!("[GEMINI] Full prompt preview (first 200 chars): {}", &full_prompt[..full_prompt.len().min(200)]);
-    
-    if let Some(window) = app_handle.get_webview_window("main") {
-        let display_prompt = if prompt.len() > 100 {
-            format!("{}...", &prompt[..100])
-        } else {
-            prompt.clone()
-        };
-        
-        let _ = window.emit(
-            "terminal-output",
-            TerminalOutput {
-                message: format!("❯ {}", display_prompt),
-                stream: "system".to_string(),
-            },
-        );
-        
-        let _ = window.emit(
-            "terminal-output",
-            TerminalOutput {
-                message: "⏳ Processando...".to_string(),
-                stream: "system".to_string(),
-            },
-        );
+    match &file_content {
+        Some(content) => println!("[GEMINI] File content length: {}", content.len()),
+        None => println!("[GEMINI] No file content provided"),
     }
     
-    let app_handle_clone = app_handle.clone();
-    let model = GEMINI_STATE.current_model.read().await.clone();
+    let options = options.unwrap_or_default();
+    let template = Template::parse(&prompt)?;
+
+    // Only the masked copies are sent, the originals are kept for the diff
+    let mut pipeline = RequestPipeline::new(options.redaction.as_ref())?;
+    let variables = pipeline.redact_variables(&options.variables.clone().unwrap_or_default());
+    let sent_content = file_content.as_deref().map(|content| pipeline.redact(content));
+
+    let (engine, model, reporter) = prepare_job(app_handle, tool_id, on_event).await?;
+    
+    reporter.started();
+    reporter.output(format!("❯ {}", preview(&prompt, 100)), "system");
+    pipeline.report_masking(&reporter);
+    reporter.output("⏳ Processando...", "system");
     
-    tokio::spawn(async move {
-        println!("[GEMINI] Background task started");
-        match execute_gemini_command_streaming(&gemini_path, &full_prompt, Some(&model), app_handle_clone.clone(), 120).await {
+    let job_id = spawn_job(reporter, |reporter| async move {
+        println!("[GEMINI] Job {} started ({} / {})", reporter.job_id(), engine.name(), model);
+        let result = generate_for_document(
+            engine.as_ref(),
+            &template,
+            &variables,
+            sent_content.as_deref(),
+            &model,
+            &options,
+            &reporter,
+        )
+        .await;
+        match result {
             Ok(complete_content) => {
+                // Rewrite tools ask for a diff, so only their answers must keep the length
+                let sources = [
+                    file_content.as_deref().unwrap_or_default(),
+                    options
+                        .variables
+                        .as_ref()
+                        .and_then(|v| v.selection.as_deref())
+                        .unwrap_or_default(),
+                ];
+                let rewritten = file_content
+                    .as_deref()
+                    .filter(|_| options.diff_granularity.is_some());
+                reporter.output("─────────────────────────────────", "system");
+                let checked = pipeline.finish(&complete_content, &sources, rewritten, &reporter);
+                let complete_content = checked.content;
                 println!("[GEMINI] Got complete content: {} chars", complete_content.len());
-                println!("[GEMINI] Content preview: {}", &complete_content[..complete_content.len().min(100)]);
+                println!("[GEMINI] Content preview: {}", preview(&complete_content, 100));
+                reporter.output("✅ Concluído", "system");
                 
-                if let Some(window) = app_handle_clone.get_webview_window("main") {
-                    let _ = window.emit(
-                        "terminal-output",
-                        TerminalOutput {
-                            message: "─────────────────────────────────".to_string(),
-                            stream: "system".to_string(),
-                        },
-                    );
-                    
-                    let _ = window.emit(
-                        "terminal-output",
-                        TerminalOutput {
-                            message: "✅ Concluído".to_string(),
-                            stream: "system".to_string(),
-                        },
-                    );
-                    
-                    println!("[GEMINI] About to emit gemini-complete event");
-                    let payload = GeminiComplete {
-                        content: complete_content.clone(),
-                    };
-                    println!("[GEMINI] Payload content length: {}", payload.content.len());
-                    
-                    match app_handle_clone.emit("gemini-complete", payload) {
-                        Ok(_) => println!("[GEMINI] Event emitted successfully"),
-                        Err(e) => println!("[GEMINI] Failed to emit event: {:?}", e),
+                // Rewrite tools ask for a diff so the change can be reviewed hunk by hunk
+                let diff = match (&file_content, options.diff_granularity) {
+                    (Some(original), Some(granularity)) => {
+                        Some(diff_texts(original, &complete_content, granularity))
                     }
-                }
+                    _ => None,
+                };
+                reporter.completed(
+                    complete_content,
+                    CompletionExtras {
+                        diff,
+                        injection_warning: checked.injection_warning,
+                        ..Default::default()
+                    },
+                );
             }
             Err(e) => {
                 println!("[GEMINI] Error in background task: {}", e);
-                if let Some(window) = app_handle_clone.get_webview_window("main") {
-                    let _ = window.emit(
-                        "terminal-output",
-                        TerminalOutput {
-                            message: format!("❌ Erro: {}", e),
-                            stream: "stderr".to_string(),
-                        },
-                    );
-                }
+                reporter.failed(&e);
             }
         }
-        println!("[GEMINI] Background task completed");
+        println!("[GEMINI] Job {} completed in {}ms", reporter.job_id(), reporter.elapsed_ms());
     });
     
+    Ok(job_id)
+}
+
+#[tauri::command]
+pub async fn cancel_gemini_job(job_id: String) -> Result<(), String> {
+    let Some(reporter) = cancel_job(&job_id) else {
+        return Err(format!("No running job with id '{}'", job_id));
+    };
+
+    reporter.output("⛔ Cancelado", "system");
+    reporter.cancelled();
     Ok(())
 }
 
 #[tauri::command]
-pub async fn set_gemini_model(model: String) -> Result<(), String> {
-    let valid_models = vec![
-        "gemini-2.5-flash",
-        "gemini-2.5-flash-lite",
-        "gemini-2.5-pro",
-    ];
-    
-    if !valid_models.contains(&model.as_str()) {
-        return Err(format!(
-            "Invalid model '{}'. Valid models: {}",
-            model,
-            valid_models.join(", ")
-        ));
-    }
-    
-    *GEMINI_STATE.current_model.write().await = model.clone();
+pub async fn set_gemini_model(app_handle: AppHandle, model: String) -> Result<(), String> {
+    let provider = ENGINE_STATE.provider.read().await.clone();
+    validate_model(&provider, &model).await?;
+
+    *ENGINE_STATE.current_model.write().await = model.clone();
     println!("[GEMINI] Model changed to: {}", model);
-    Ok(())
+    save_engine_settings(&app_handle)
+        .await
+        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub async fn get_gemini_model() -> Result<String, String> {
-    Ok(GEMINI_STATE.current_model.read().await.clone())
+    Ok(ENGINE_STATE.current_model.read().await.clone())
 }
\ No newline at end of file
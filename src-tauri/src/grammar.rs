@@ -0,0 +1,321 @@
+// src-tauri/src/grammar.rs
+
+use serde::{Deserialize, Serialize};
+use tauri::ipc::Channel;
+use tauri::AppHandle;
+use uuid::Uuid;
+
+use crate::diff::utf16_len;
+use crate::engine::EngineRequest;
+use crate::errors::{AiError, ClarezaError};
+use crate::fencing::Fence;
+use crate::gemini::prepare_job;
+use crate::jobs::{spawn_job, CompletionExtras, JobEvent};
+use crate::pipeline::RequestPipeline;
+use crate::redaction::RedactionOptions;
+use crate::retry::RetryPolicy;
+
+const GRAMMAR_PROMPT: &str = "Você é um revisor gramatical de português do Brasil. \
+Analise o texto abaixo e aponte apenas erros de ortografia, gramática, concordância, \
+regência, crase e pontuação. Não sugira mudanças de estilo ou vocabulário autoral. \
+Responda SOMENTE com um array JSON, sem markdown e sem comentários, em que cada item tem os campos: \
+\"original\" (trecho exato copiado do texto, curto mas com palavras suficientes para ser único), \
+\"replacement\" (o mesmo trecho corrigido), \
+\"category\" (um de: spelling, grammar, agreement, punctuation, style) e \
+\"explanation\" (explicação curta em português). \
+Se não houver erros, responda [].";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SuggestionCategory {
+    Spelling,
+    Grammar,
+    Agreement,
+    Punctuation,
+    Style,
+    #[serde(other)]
+    Other,
+}
+
+/// A single correction anchored to the document it was computed for.
+///
+/// `byte_*` offsets index the UTF-8 string, `char_*` offsets count UTF-16
+/// code units like the editor does.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GrammarSuggestion {
+    pub id: String,
+    pub byte_start: usize,
+    pub byte_end: usize,
+    pub char_start: usize,
+    pub char_end: usize,
+    pub original: String,
+    pub replacement: String,
+    pub category: SuggestionCategory,
+    pub explanation: String,
+}
+
+/// Suggestion as returned by the model, before it is located in the text.
+#[derive(Debug, Deserialize)]
+struct RawSuggestion {
+    original: String,
+    replacement: String,
+    #[serde(default = "default_category")]
+    category: SuggestionCategory,
+    #[serde(default)]
+    explanation: String,
+}
+
+fn default_category() -> SuggestionCategory {
+    SuggestionCategory::Other
+}
+
+fn build_grammar_prompt(content: &str) -> String {
+    let fence = Fence::for_contents(&[content]);
+    format!(
+        "{}{}\n\nTexto:\n{}",
+        fence.preamble(),
+        GRAMMAR_PROMPT,
+        fence.wrap("texto", content)
+    )
+}
+
+/// Extract the JSON array from a model response, tolerating code fences and
+/// chatter around it.
+fn parse_raw_suggestions(response: &str) -> Result<Vec<RawSuggestion>, AiError> {
+    let start = response.find('[');
+    let end = response.rfind(']');
+    let json = match (start, end) {
+        (Some(start), Some(end)) if start < end => &response[start..=end],
+        _ => {
+            return Err(AiError::Other(
+                "Model response does not contain a JSON array".to_string(),
+            ))
+        }
+    };
+
+    serde_json::from_str(json)
+        .map_err(|e| AiError::Other(format!("Invalid suggestions JSON: {}", e)))
+}
+
+/// Find where each suggestion applies in `content`.
+///
+/// Models are unreliable with offsets, so only the quoted `original` text is
+/// trusted. Suggestions are matched in order, preferring the first occurrence
+/// after the previous match; ones that cannot be found or would overlap an
+/// earlier suggestion are dropped.
+fn locate_suggestions(content: &str, raw: Vec<RawSuggestion>) -> Vec<GrammarSuggestion> {
+    let mut suggestions: Vec<GrammarSuggestion> = Vec::new();
+    let mut cursor = 0;
+
+    for item in raw {
+        if item.original.is_empty() || item.original == item.replacement {
+            continue;
+        }
+
+        let len = item.original.len();
+        let is_free = |start: usize| {
+            suggestions
+                .iter()
+                .all(|s| start + len <= s.byte_start || start >= s.byte_end)
+        };
+        let occurrences: Vec<usize> = content
+            .match_indices(item.original.as_str())
+            .map(|(idx, _)| idx)
+            .collect();
+
+        let Some(start) = occurrences
+            .iter()
+            .copied()
+            .find(|&idx| idx >= cursor && is_free(idx))
+            .or_else(|| occurrences.iter().copied().find(|&idx| is_free(idx)))
+        else {
+            println!("[GRAMMAR] Could not locate '{}' in document", item.original);
+            continue;
+        };
+
+        let char_start = utf16_len(&content[..start]);
+        cursor = start + len;
+        suggestions.push(GrammarSuggestion {
+            id: Uuid::new_v4().to_string(),
+            byte_start: start,
+            byte_end: start + len,
+            char_start,
+            char_end: char_start + utf16_len(&item.original),
+            original: item.original,
+            replacement: item.replacement,
+            category: item.category,
+            explanation: item.explanation,
+        });
+    }
+
+    suggestions.sort_by_key(|s| s.byte_start);
+    suggestions
+}
+
+/// Apply suggestions to the text they were computed for. Suggestions whose
+/// range no longer matches `original` are skipped.
+pub fn apply_suggestions(content: &str, suggestions: &[GrammarSuggestion]) -> String {
+    let mut sorted: Vec<&GrammarSuggestion> = suggestions.iter().collect();
+    sorted.sort_by_key(|s| s.byte_start);
+
+    let mut result = String::with_capacity(content.len());
+    let mut last = 0;
+    for suggestion in sorted {
+        let matches = content
+            .get(suggestion.byte_start..suggestion.byte_end)
+            .is_some_and(|slice| slice == suggestion.original);
+        if !matches || suggestion.byte_start < last {
+            continue;
+        }
+        result.push_str(&content[last..suggestion.byte_start]);
+        result.push_str(&suggestion.replacement);
+        last = suggestion.byte_end;
+    }
+    result.push_str(&content[last..]);
+    result
+}
+
+/// Run the grammar check as a job. The completion, sent as
+/// `grammar-complete`, carries only the individual `suggestions`: the user
+/// accepts them one by one through `apply_grammar_suggestions`.
+#[tauri::command]
+pub async fn check_grammar(
+    app_handle: AppHandle,
+    content: String,
+    redaction: Option<RedactionOptions>,
+    on_event: Option<Channel<JobEvent>>,
+) -> Result<String, ClarezaError> {
+    println!("[GRAMMAR] Checking {} chars", content.len());
+
+    let mut pipeline = RequestPipeline::new(redaction.as_ref())?;
+    let sent_content = pipeline.redact(&content);
+
+    let (engine, model, reporter) =
+        prepare_job(app_handle, Some("grammar".to_string()), on_event).await?;
+    let request = EngineRequest {
+        prompt: build_grammar_prompt(&sent_content),
+        model,
+        timeout_secs: 120,
+        document_tools: pipeline.document_tools(),
+    };
+
+    reporter.started();
+    reporter.output("❯ Verificando gramática...", "system");
+    pipeline.report_masking(&reporter);
+
+    let job_id = spawn_job(reporter, |reporter| async move {
+        // Restored before parsing, so quoted originals match the real text
+        let result = pipeline
+            .generate(
+                engine.as_ref(),
+                &request,
+                &RetryPolicy::default(),
+                false,
+                &[&content],
+                None,
+                &reporter,
+            )
+            .await
+            .and_then(|checked| {
+                parse_raw_suggestions(&checked.content).map(|raw| (raw, checked.injection_warning))
+            });
+
+        match result {
+            Ok((raw, injection_warning)) => {
+                let suggestions = locate_suggestions(&content, raw);
+                println!("[GRAMMAR] {} suggestions located", suggestions.len());
+                reporter.output(
+                    format!("✅ {} sugestões encontradas", suggestions.len()),
+                    "system",
+                );
+                reporter.completed_as(
+                    "grammar-complete",
+                    String::new(),
+                    CompletionExtras {
+                        suggestions: Some(suggestions),
+                        injection_warning,
+                        ..Default::default()
+                    },
+                );
+            }
+            Err(e) => {
+                println!("[GRAMMAR] Error: {}", e);
+                reporter.failed(&e);
+            }
+        }
+    });
+
+    Ok(job_id)
+}
+
+/// Apply the suggestions the user accepted and return the new text.
+#[tauri::command]
+pub fn apply_grammar_suggestions(
+    content: String,
+    suggestions: Vec<GrammarSuggestion>,
+) -> Result<String, String> {
+    Ok(apply_suggestions(&content, &suggestions))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw(original: &str, replacement: &str) -> RawSuggestion {
+        RawSuggestion {
+            original: original.to_string(),
+            replacement: replacement.to_string(),
+            category: SuggestionCategory::Spelling,
+            explanation: String::new(),
+        }
+    }
+
+    #[test]
+    fn parses_json_surrounded_by_chatter() {
+        let response = "Aqui está:\n```json\n[{\"original\": \"caza\", \"replacement\": \"casa\", \
+\"category\": \"novidade\"}]\n```";
+        let parsed = parse_raw_suggestions(response).unwrap();
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].category, SuggestionCategory::Other);
+        assert!(parse_raw_suggestions("Nenhum erro encontrado.").is_err());
+    }
+
+    #[test]
+    fn locates_repeated_text_in_order() {
+        let content = "A 🙂 menina foi a escola. A menina gostou.";
+        let suggestions = locate_suggestions(
+            content,
+            vec![raw("foi a escola", "foi à escola"), raw("A menina", "A garota")],
+        );
+        assert_eq!(suggestions.len(), 2);
+
+        let second = &suggestions[1];
+        assert_eq!(&content[second.byte_start..second.byte_end], "A menina");
+        assert_eq!(second.byte_start, content.rfind("A menina").unwrap());
+        assert_eq!(second.char_start, utf16_len(&content[..second.byte_start]));
+        assert_eq!(second.char_end - second.char_start, 8);
+    }
+
+    #[test]
+    fn drops_unknown_overlapping_and_empty_suggestions() {
+        let content = "Eles vai embora.";
+        let suggestions = locate_suggestions(
+            content,
+            vec![
+                raw("Eles vai", "Eles vão"),
+                raw("vai embora", "vão embora"),
+                raw("inexistente", "outro"),
+                raw("embora", "embora"),
+            ],
+        );
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(apply_suggestions(content, &suggestions), "Eles vão embora.");
+    }
+
+    #[test]
+    fn skips_suggestions_for_changed_text() {
+        let suggestions = locate_suggestions("Eu fasso.", vec![raw("fasso", "faço")]);
+        assert_eq!(apply_suggestions("Eu fasso.", &suggestions), "Eu faço.");
+        assert_eq!(apply_suggestions("Eu já fasso.", &suggestions), "Eu já fasso.");
+    }
+}
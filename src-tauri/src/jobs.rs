@@ -0,0 +1,407 @@
+// src-tauri/src/jobs.rs
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tauri::ipc::Channel;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::task::AbortHandle;
+use uuid::Uuid;
+
+use crate::diff::{DiffHunk, TextRange};
+use crate::errors::AiError;
+use crate::grammar::GrammarSuggestion;
+
+#[derive(Clone, Serialize)]
+pub struct TerminalOutput {
+    pub message: String,
+    pub stream: String,
+    pub job_id: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct GeminiComplete {
+    pub job_id: String,
+    pub tool_id: Option<String>,
+    pub provider: String,
+    pub model: String,
+    pub content: String,
+    pub duration_ms: u64,
+    #[serde(flatten)]
+    pub extras: CompletionExtras,
+}
+
+/// Structured results some tools attach to their completion event.
+#[derive(Clone, Default, Serialize)]
+pub struct CompletionExtras {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggestions: Option<Vec<GrammarSuggestion>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diff: Option<Vec<DiffHunk>>,
+    /// Range of the document that `content` replaces, for selection jobs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub selection: Option<TextRange>,
+    /// Set when the response looks like it followed instructions embedded in
+    /// the document rather than the tool's.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub injection_warning: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct GeminiStarted {
+    pub job_id: String,
+    pub tool_id: Option<String>,
+    pub provider: String,
+    pub model: String,
+    pub started_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct GeminiFailed {
+    pub job_id: String,
+    pub tool_id: Option<String>,
+    pub error: String,
+    /// Machine-readable category, see `AiError::kind`.
+    pub kind: String,
+    pub hint: String,
+    pub duration_ms: u64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct GeminiCancelled {
+    pub job_id: String,
+    pub tool_id: Option<String>,
+    pub duration_ms: u64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct GeminiProgress {
+    pub job_id: String,
+    pub message: String,
+    pub stream: String,
+    pub elapsed_ms: u64,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChunkStatus {
+    Started,
+    Completed,
+    Failed,
+}
+
+#[derive(Clone, Serialize)]
+pub struct GeminiChunkProgress {
+    pub job_id: String,
+    pub chunk_index: usize,
+    pub total_chunks: usize,
+    pub status: ChunkStatus,
+    pub elapsed_ms: u64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct GeminiRetry {
+    pub job_id: String,
+    /// The attempt about to start, counting from 1.
+    pub attempt: u32,
+    pub max_attempts: u32,
+    pub delay_ms: u64,
+    pub model: String,
+    pub kind: String,
+    pub error: String,
+}
+
+/// Lifecycle of a single job, sent over the channel passed by the invoking call.
+#[derive(Clone, Serialize)]
+#[serde(tag = "event", content = "data", rename_all = "kebab-case")]
+pub enum JobEvent {
+    Started(GeminiStarted),
+    Progress(GeminiProgress),
+    ChunkProgress(GeminiChunkProgress),
+    Retry(GeminiRetry),
+    Completed(GeminiComplete),
+    Failed(GeminiFailed),
+    Cancelled(GeminiCancelled),
+}
+
+struct JobInfo {
+    job_id: String,
+    tool_id: Option<String>,
+    provider: String,
+    model: String,
+    started_at: DateTime<Utc>,
+    started: Instant,
+    app_handle: AppHandle,
+    channel: Option<Channel<JobEvent>>,
+    /// Set by the first terminal event: completion, failure or cancellation.
+    finished: AtomicBool,
+}
+
+/// Emits every event of one job, both on its own channel (when the caller
+/// supplied one) and as the global window events older listeners rely on.
+#[derive(Clone)]
+pub struct JobReporter {
+    info: Arc<JobInfo>,
+}
+
+impl JobReporter {
+    pub fn new(
+        app_handle: AppHandle,
+        tool_id: Option<String>,
+        provider: &str,
+        model: &str,
+        channel: Option<Channel<JobEvent>>,
+    ) -> Self {
+        Self {
+            info: Arc::new(JobInfo {
+                job_id: Uuid::new_v4().to_string(),
+                tool_id,
+                provider: provider.to_string(),
+                model: model.to_string(),
+                started_at: Utc::now(),
+                started: Instant::now(),
+                app_handle,
+                channel,
+                finished: AtomicBool::new(false),
+            }),
+        }
+    }
+
+    pub fn job_id(&self) -> &str {
+        &self.info.job_id
+    }
+
+    pub fn tool_id(&self) -> Option<&str> {
+        self.info.tool_id.as_deref()
+    }
+
+    pub fn app_handle(&self) -> &AppHandle {
+        &self.info.app_handle
+    }
+
+    pub fn elapsed_ms(&self) -> u64 {
+        self.info.started.elapsed().as_millis() as u64
+    }
+
+    /// Claim the job's single terminal event. False if another one was
+    /// already sent, e.g. a completion racing a cancellation.
+    fn finish(&self) -> bool {
+        if self.info.finished.swap(true, Ordering::SeqCst) {
+            return false;
+        }
+        RUNNING_JOBS.lock().unwrap().remove(&self.info.job_id);
+        true
+    }
+
+    fn send(&self, event: JobEvent) {
+        if let Some(channel) = &self.info.channel {
+            let _ = channel.send(event);
+        }
+    }
+
+    fn emit_global<S: Serialize + Clone>(&self, event: &str, payload: S) {
+        if let Some(window) = self.info.app_handle.get_webview_window("main") {
+            let _ = window.emit(event, payload);
+        }
+    }
+
+    pub fn started(&self) {
+        let payload = GeminiStarted {
+            job_id: self.info.job_id.clone(),
+            tool_id: self.info.tool_id.clone(),
+            provider: self.info.provider.clone(),
+            model: self.info.model.clone(),
+            started_at: self.info.started_at,
+        };
+        self.emit_global("gemini-started", payload.clone());
+        self.send(JobEvent::Started(payload));
+    }
+
+    /// A line of output for the terminal view (`stdout`, `stderr` or `system`).
+    pub fn output(&self, message: impl Into<String>, stream: &str) {
+        let message = message.into();
+        self.emit_global(
+            "terminal-output",
+            TerminalOutput {
+                message: message.clone(),
+                stream: stream.to_string(),
+                job_id: Some(self.info.job_id.clone()),
+            },
+        );
+        self.send(JobEvent::Progress(GeminiProgress {
+            job_id: self.info.job_id.clone(),
+            message,
+            stream: stream.to_string(),
+            elapsed_ms: self.elapsed_ms(),
+        }));
+    }
+
+    /// Status change of one chunk of a document processed in parts.
+    pub fn chunk_progress(&self, chunk_index: usize, total_chunks: usize, status: ChunkStatus) {
+        let payload = GeminiChunkProgress {
+            job_id: self.info.job_id.clone(),
+            chunk_index,
+            total_chunks,
+            status,
+            elapsed_ms: self.elapsed_ms(),
+        };
+        self.emit_global("gemini-chunk-progress", payload.clone());
+        self.send(JobEvent::ChunkProgress(payload));
+    }
+
+    /// A transient failure is about to be retried after `delay_ms`.
+    pub fn retrying(
+        &self,
+        attempt: u32,
+        max_attempts: u32,
+        delay_ms: u64,
+        model: &str,
+        error: &AiError,
+    ) {
+        self.output(
+            format!(
+                "🔁 {} Nova tentativa {}/{} em {}s...",
+                error,
+                attempt,
+                max_attempts,
+                delay_ms.div_ceil(1000)
+            ),
+            "system",
+        );
+
+        let payload = GeminiRetry {
+            job_id: self.info.job_id.clone(),
+            attempt,
+            max_attempts,
+            delay_ms,
+            model: model.to_string(),
+            kind: error.kind().to_string(),
+            error: error.to_string(),
+        };
+        self.emit_global("gemini-retry", payload.clone());
+        self.send(JobEvent::Retry(payload));
+    }
+
+    /// Completion of a job whose `content` is the whole new document.
+    pub fn completed(&self, content: String, extras: CompletionExtras) {
+        self.completed_as("gemini-complete", content, extras);
+    }
+
+    /// Completion under another global event name. Tools whose result is
+    /// not a whole document (a selection, suggestions) use this, so listeners
+    /// that replace the open document with `content` never receive it.
+    pub fn completed_as(&self, event: &str, content: String, extras: CompletionExtras) {
+        if !self.finish() {
+            return;
+        }
+        let payload = GeminiComplete {
+            job_id: self.info.job_id.clone(),
+            tool_id: self.info.tool_id.clone(),
+            provider: self.info.provider.clone(),
+            model: self.info.model.clone(),
+            content,
+            duration_ms: self.elapsed_ms(),
+            extras,
+        };
+        // Sent to every window, as before job IDs existed
+        match self.info.app_handle.emit(event, payload.clone()) {
+            Ok(_) => println!("[JOBS] Job {} complete event emitted", self.info.job_id),
+            Err(e) => println!("[JOBS] Failed to emit complete event: {:?}", e),
+        }
+        self.send(JobEvent::Completed(payload));
+    }
+
+    /// Report the failure in the terminal view and as a typed event.
+    pub fn failed(&self, error: &AiError) {
+        if !self.finish() {
+            return;
+        }
+        self.output(format!("❌ Erro: {}", error), "stderr");
+        self.output(format!("💡 {}", error.hint()), "system");
+
+        let payload = GeminiFailed {
+            job_id: self.info.job_id.clone(),
+            tool_id: self.info.tool_id.clone(),
+            error: error.to_string(),
+            kind: error.kind().to_string(),
+            hint: error.hint().to_string(),
+            duration_ms: self.elapsed_ms(),
+        };
+        self.emit_global("gemini-failed", payload.clone());
+        self.send(JobEvent::Failed(payload));
+    }
+
+    pub fn cancelled(&self) {
+        let payload = GeminiCancelled {
+            job_id: self.info.job_id.clone(),
+            tool_id: self.info.tool_id.clone(),
+            duration_ms: self.elapsed_ms(),
+        };
+        self.emit_global("gemini-cancelled", payload.clone());
+        self.send(JobEvent::Cancelled(payload));
+    }
+}
+
+/// A background AI job that can still be cancelled.
+struct RunningJob {
+    abort_handle: AbortHandle,
+    reporter: JobReporter,
+}
+
+static RUNNING_JOBS: Lazy<Mutex<HashMap<String, RunningJob>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Spawn a job on the tokio runtime and register it under the reporter's ID.
+///
+/// The job removes itself from the registry when it finishes. Aborting it
+/// drops its future, which kills any child process spawned with
+/// `kill_on_drop(true)`.
+pub fn spawn_job<F, Fut>(reporter: JobReporter, make_job: F) -> String
+where
+    F: FnOnce(JobReporter) -> Fut,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let job_id = reporter.job_id().to_string();
+    let job = make_job(reporter.clone());
+    let registry_id = job_id.clone();
+
+    // Hold the lock while spawning so the job cannot unregister itself
+    // before it has been registered
+    let mut jobs = RUNNING_JOBS.lock().unwrap();
+    let handle = tokio::spawn(async move {
+        job.await;
+        RUNNING_JOBS.lock().unwrap().remove(&registry_id);
+    });
+    jobs.insert(
+        job_id.clone(),
+        RunningJob {
+            abort_handle: handle.abort_handle(),
+            reporter,
+        },
+    );
+
+    job_id
+}
+
+/// Abort a running job and hand back its reporter so the caller can announce
+/// the cancellation. Returns `None` if no such job is running, or if it has
+/// already reported its completion or failure.
+pub fn cancel_job(job_id: &str) -> Option<JobReporter> {
+    let job = RUNNING_JOBS.lock().unwrap().remove(job_id)?;
+    if job.reporter.info.finished.swap(true, Ordering::SeqCst) {
+        return None;
+    }
+
+    job.abort_handle.abort();
+    println!(
+        "[JOBS] Cancelled job {} after {}ms",
+        job_id,
+        job.reporter.elapsed_ms()
+    );
+    Some(job.reporter)
+}
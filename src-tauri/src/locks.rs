@@ -0,0 +1,276 @@
+// src-tauri/src/locks.rs
+
+use chrono::{DateTime, Utc};
+use fs4::FileExt;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::errors::ClarezaError;
+use crate::utils::FileUtils;
+
+/// Same naming as LibreOffice, hidden on unix and recognisable on shared drives.
+const SIDECAR_PREFIX: &str = ".~lock.";
+const SIDECAR_SUFFIX: &str = "#";
+
+/// Resolved at startup by `resolve_local_host`: the last resort runs a
+/// process, which must not happen on first use under `HELD_LOCKS`.
+static LOCAL_HOST: Lazy<String> = Lazy::new(|| {
+    ["COMPUTERNAME", "HOSTNAME"]
+        .iter()
+        .find_map(|var| std::env::var(var).ok())
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .or_else(|| {
+            std::process::Command::new("hostname")
+                .output()
+                .ok()
+                .map(|output| String::from_utf8_lossy(&output.stdout).to_string())
+        })
+        .map(|host| host.trim().to_string())
+        .filter(|host| !host.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+});
+
+/// Look up the host name recorded in lock sidecars. Called once at startup.
+pub fn resolve_local_host() {
+    println!("[LOCK] Host: {}", *LOCAL_HOST);
+}
+
+/// Sidecars held by this process, keyed by document. Dropping the file
+/// releases the OS lock.
+static HELD_LOCKS: Lazy<Mutex<HashMap<PathBuf, HeldLock>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+struct HeldLock {
+    file: File,
+    sidecar: PathBuf,
+}
+
+/// Who has a document open, as recorded in its lock sidecar.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockOwner {
+    pub user: String,
+    pub host: String,
+    pub pid: u32,
+    pub opened_at: DateTime<Utc>,
+}
+
+impl LockOwner {
+    fn current() -> Self {
+        Self {
+            user: std::env::var("USER")
+                .or_else(|_| std::env::var("USERNAME"))
+                .unwrap_or_else(|_| "unknown".to_string()),
+            host: LOCAL_HOST.clone(),
+            pid: std::process::id(),
+            opened_at: Utc::now(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LockStatus {
+    pub locked: bool,
+    /// Missing when the sidecar cannot be read, e.g. on Windows, where the
+    /// holder's lock also blocks reading it.
+    pub owner: Option<LockOwner>,
+}
+
+fn sidecar_path(key: &Path) -> PathBuf {
+    let name = key
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    key.with_file_name(format!("{}{}{}", SIDECAR_PREFIX, name, SIDECAR_SUFFIX))
+}
+
+fn read_owner(sidecar: &Path) -> Option<LockOwner> {
+    let content = std::fs::read_to_string(sidecar).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn open_sidecar(sidecar: &Path) -> std::io::Result<File> {
+    OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(sidecar)
+}
+
+fn is_contended(e: &std::io::Error) -> bool {
+    e.raw_os_error() == fs4::lock_contended_error().raw_os_error()
+}
+
+fn write_owner(file: &File) -> Result<(), ClarezaError> {
+    let owner = serde_json::to_string(&LockOwner::current())?;
+    file.set_len(0)?;
+    let mut file = file;
+    file.write_all(owner.as_bytes())?;
+    Ok(())
+}
+
+/// Lock `key` for this process, or report who holds it.
+fn try_acquire(key: &Path) -> Result<Result<HeldLock, LockStatus>, ClarezaError> {
+    let sidecar = sidecar_path(key);
+    // A holder releasing at the same moment removes the sidecar we opened,
+    // so the lock is only trusted if the file is still there afterwards
+    for _ in 0..3 {
+        let file = open_sidecar(&sidecar)?;
+        // Qualified, recent std has inherent lock methods with other signatures
+        match FileExt::try_lock_exclusive(&file) {
+            Ok(()) if sidecar.exists() => {
+                // Whatever was recorded belongs to a process that is gone
+                write_owner(&file)?;
+                return Ok(Ok(HeldLock { file, sidecar }));
+            }
+            Ok(()) => continue,
+            Err(e) if is_contended(&e) => {
+                return Ok(Err(LockStatus {
+                    locked: true,
+                    owner: read_owner(&sidecar),
+                }));
+            }
+            Err(e) => {
+                // Some network file systems do not support locks, only the
+                // sidecar tells who has the file open. A stale one cannot be
+                // told apart there, unless it was left on this machine.
+                println!("[LOCK] Locking not supported for {}: {}", key.display(), e);
+                if let Some(owner) = read_owner(&sidecar).filter(|o| o.host != *LOCAL_HOST) {
+                    return Ok(Err(LockStatus {
+                        locked: true,
+                        owner: Some(owner),
+                    }));
+                }
+                write_owner(&file)?;
+                return Ok(Ok(HeldLock { file, sidecar }));
+            }
+        }
+    }
+    Err(ClarezaError::Locked(format!(
+        "{} could not be locked",
+        key.display()
+    )))
+}
+
+fn describe(owner: Option<&LockOwner>) -> String {
+    match owner {
+        Some(owner) => format!(
+            "opened by {} on {} (pid {}) since {}",
+            owner.user,
+            owner.host,
+            owner.pid,
+            owner.opened_at.format("%Y-%m-%d %H:%M")
+        ),
+        None => "opened by another program".to_string(),
+    }
+}
+
+/// Take the advisory lock on a document before it is opened for editing or
+/// saved. Holding it already is fine; another holder is a `Locked` error.
+pub fn acquire_document_lock(path: &Path) -> Result<(), ClarezaError> {
+    let key = FileUtils::document_key(path);
+    let mut held = HELD_LOCKS
+        .lock()
+        .map_err(|_| ClarezaError::Path("Lock registry poisoned".to_string()))?;
+    if held.contains_key(&key) {
+        return Ok(());
+    }
+    match try_acquire(&key)? {
+        Ok(lock) => {
+            println!("[LOCK] Locked {}", key.display());
+            held.insert(key, lock);
+            Ok(())
+        }
+        Err(status) => Err(ClarezaError::Locked(format!(
+            "{} is {}",
+            key.display(),
+            describe(status.owner.as_ref())
+        ))),
+    }
+}
+
+fn release(lock: HeldLock) {
+    // Removed while still locked, see `try_acquire`
+    let _ = std::fs::remove_file(&lock.sidecar);
+    let _ = FileExt::unlock(&lock.file);
+}
+
+/// Release every lock this process holds. Called on exit; the OS would drop
+/// the locks anyway, but the sidecars would be left on shared drives.
+pub fn release_all_locks() {
+    if let Ok(mut held) = HELD_LOCKS.lock() {
+        for (_, lock) in held.drain() {
+            release(lock);
+        }
+    }
+}
+
+/// Whether someone else has `path` open, so the UI can offer to open it
+/// read-only.
+#[tauri::command]
+pub async fn check_document_lock(path: String) -> Result<LockStatus, ClarezaError> {
+    let key = FileUtils::document_key(Path::new(&path));
+    let held = HELD_LOCKS
+        .lock()
+        .map_err(|_| ClarezaError::Path("Lock registry poisoned".to_string()))?;
+    let sidecar = sidecar_path(&key);
+    if held.contains_key(&key) || !sidecar.exists() {
+        return Ok(LockStatus {
+            locked: false,
+            owner: None,
+        });
+    }
+
+    let file = match File::open(&sidecar) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            return Ok(LockStatus {
+                locked: false,
+                owner: None,
+            })
+        }
+        Err(e) => return Err(e.into()),
+    };
+    match FileExt::try_lock_shared(&file) {
+        // Left behind by a process that did not exit cleanly
+        Ok(()) => {
+            let _ = FileExt::unlock(&file);
+            Ok(LockStatus {
+                locked: false,
+                owner: None,
+            })
+        }
+        Err(e) if is_contended(&e) => Ok(LockStatus {
+            locked: true,
+            owner: read_owner(&sidecar),
+        }),
+        // Cannot tell, trust the sidecar
+        Err(_) => {
+            let owner = read_owner(&sidecar);
+            Ok(LockStatus {
+                locked: owner.is_some(),
+                owner,
+            })
+        }
+    }
+}
+
+/// Release the lock on a document, typically when it is closed.
+#[tauri::command]
+pub async fn release_document_lock(path: String) -> Result<(), ClarezaError> {
+    let key = FileUtils::document_key(Path::new(&path));
+    let lock = HELD_LOCKS
+        .lock()
+        .map_err(|_| ClarezaError::Path("Lock registry poisoned".to_string()))?
+        .remove(&key);
+    if let Some(lock) = lock {
+        release(lock);
+        println!("[LOCK] Released {}", key.display());
+    }
+    Ok(())
+}
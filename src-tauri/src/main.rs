@@ -2,27 +2,78 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod cache;
 mod checks;
+mod chunking;
 mod commands;
+mod compare;
+mod diff;
+mod encoding;
+mod engine;
 mod errors;
+mod fencing;
 mod gemini;
+mod grammar;
+mod jobs;
+mod locks;
+mod mcp;
 mod models;
+mod openai_compat;
+mod pipeline;
+mod prompts;
+mod recent;
+mod redaction;
+mod retry;
+mod selection;
+mod sessions;
+mod templates;
+mod usage;
 mod utils;
+mod watcher;
+mod workspace;
 
 #[tauri::command]
 fn ping() -> String {
     "pong".into()
 }
 
+use cache::{get_cache_stats, list_cache_entries, purge_cache};
 use checks::{check_bun, check_gemini, install_bun, install_gemini};
 
 use commands::{
     create_backup, create_document, debug_get_path, get_document_version, get_document_versions,
-    get_recent_files, list_backups, open_document, open_terminal, restore_backup, save_document,
-    save_document_as, show_open_dialog, validate_path,
+    list_backups, open_document, open_terminal, restore_backup, save_document, save_document_as,
+    show_open_dialog, validate_path,
 };
 
-use gemini::{get_gemini_model, send_prompt_to_gemini, set_gemini_model};
+use compare::compare_models;
+use diff::diff_documents;
+use engine::{
+    get_ai_provider, get_tool_models, list_available_models, set_ai_provider, set_tool_model,
+};
+use gemini::{cancel_gemini_job, get_gemini_model, send_prompt_to_gemini, set_gemini_model};
+use grammar::{apply_grammar_suggestions, check_grammar};
+use locks::{check_document_lock, release_document_lock};
+use mcp::{
+    close_mcp_document, dismiss_proposed_edits, get_mcp_server_info, list_proposed_edits,
+    publish_mcp_document, start_mcp_server, stop_mcp_server,
+};
+use prompts::{
+    create_prompt_tool, delete_prompt_tool, export_prompt_tools, import_prompt_tools,
+    list_prompt_tools, update_prompt_tool,
+};
+use recent::{
+    clear_recent_files, get_recent_files, pin_recent_file, remove_recent_file,
+    set_recent_files_limit,
+};
+use selection::send_selection_to_gemini;
+use sessions::{
+    branch_session, clear_session, create_session, delete_session, get_session, list_sessions,
+    send_session_prompt,
+};
+use templates::render_prompt_template;
+use usage::{clear_usage_ledger, get_usage_records, get_usage_summary};
+use watcher::{merge_with_disk, unwatch_document};
 
 fn main() {
     let _ = fix_path_env::fix();
@@ -33,6 +84,12 @@ fn main() {
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_process::init())
         .plugin(tauri_plugin_updater::Builder::new().build())
+        .setup(|app| {
+            tauri::async_runtime::block_on(engine::load_engine_settings(app.handle()));
+            workspace::purge_stale_workspaces();
+            locks::resolve_local_host();
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             // Checks
             check_gemini,
@@ -47,7 +104,6 @@ fn main() {
             restore_backup,
             get_document_versions,
             get_document_version,
-            get_recent_files,
             validate_path,
             ping,
             open_terminal,
@@ -57,9 +113,70 @@ fn main() {
             install_bun,
             // Gemini CLI commands
             send_prompt_to_gemini,
+            send_selection_to_gemini,
+            cancel_gemini_job,
+            check_grammar,
+            apply_grammar_suggestions,
+            diff_documents,
+            compare_models,
             set_gemini_model,
             get_gemini_model,
+            // AI provider commands
+            set_ai_provider,
+            get_ai_provider,
+            list_available_models,
+            set_tool_model,
+            get_tool_models,
+            // Prompt library commands
+            list_prompt_tools,
+            create_prompt_tool,
+            update_prompt_tool,
+            delete_prompt_tool,
+            import_prompt_tools,
+            export_prompt_tools,
+            render_prompt_template,
+            // Conversation session commands
+            create_session,
+            get_session,
+            list_sessions,
+            send_session_prompt,
+            branch_session,
+            clear_session,
+            delete_session,
+            // Response cache commands
+            get_cache_stats,
+            list_cache_entries,
+            purge_cache,
+            // Usage ledger commands
+            get_usage_summary,
+            get_usage_records,
+            clear_usage_ledger,
+            // MCP server commands
+            start_mcp_server,
+            stop_mcp_server,
+            get_mcp_server_info,
+            publish_mcp_document,
+            close_mcp_document,
+            list_proposed_edits,
+            dismiss_proposed_edits,
+            // Recent files commands
+            get_recent_files,
+            pin_recent_file,
+            remove_recent_file,
+            clear_recent_files,
+            set_recent_files_limit,
+            // File watcher commands
+            merge_with_disk,
+            unwatch_document,
+            // Document lock commands
+            check_document_lock,
+            release_document_lock,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|_app, event| {
+            if let tauri::RunEvent::Exit = event {
+                locks::release_all_locks();
+            }
+        });
 }
@@ -0,0 +1,760 @@
+// src-tauri/src/mcp.rs
+
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::{oneshot, Mutex, RwLock};
+use uuid::Uuid;
+
+use crate::diff::{byte_offset, utf16_len, TextRange};
+use crate::errors::ClarezaError;
+
+/// Name the server is registered under in the Gemini CLI settings.
+pub const MCP_SERVER_NAME: &str = "clareza";
+/// Header carrying the job a CLI run belongs to, so proposals can be tied to it.
+pub const JOB_HEADER: &str = "x-clareza-job";
+
+const PROTOCOL_VERSION: &str = "2025-06-18";
+const SUPPORTED_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+/// Where a running server listens and the bearer token it expects.
+#[derive(Debug, Clone, Serialize)]
+pub struct McpServerInfo {
+    pub url: String,
+    pub token: String,
+}
+
+/// An open document as last published by the frontend.
+#[derive(Debug, Clone, Serialize)]
+pub struct McpDocument {
+    pub path: String,
+    pub title: String,
+    pub content: String,
+    pub selection: Option<TextRange>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A change suggested by the model through `propose_edit`, for the user to
+/// review like any other suggestion.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProposedEdit {
+    pub id: String,
+    pub job_id: Option<String>,
+    pub path: String,
+    pub range: TextRange,
+    pub original: String,
+    pub replacement: String,
+    pub explanation: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Default)]
+struct McpRegistry {
+    documents: HashMap<String, McpDocument>,
+    active: Option<String>,
+    proposals: Vec<ProposedEdit>,
+}
+
+static REGISTRY: Lazy<RwLock<McpRegistry>> = Lazy::new(|| RwLock::new(McpRegistry::default()));
+
+struct RunningServer {
+    info: McpServerInfo,
+    shutdown: oneshot::Sender<()>,
+}
+
+static SERVER: Lazy<Mutex<Option<RunningServer>>> = Lazy::new(|| Mutex::new(None));
+
+#[derive(Clone)]
+struct ServerState {
+    token: String,
+    /// Tells the frontend about each new proposal.
+    on_proposal: Arc<dyn Fn(&ProposedEdit) + Send + Sync>,
+}
+
+/// The running server, if any.
+pub async fn server_info() -> Option<McpServerInfo> {
+    SERVER
+        .lock()
+        .await
+        .as_ref()
+        .map(|server| server.info.clone())
+}
+
+fn range_for(content: &str, char_start: usize, char_end: usize) -> Option<TextRange> {
+    if char_start > char_end {
+        return None;
+    }
+    Some(TextRange {
+        byte_start: byte_offset(content, char_start)?,
+        byte_end: byte_offset(content, char_end)?,
+        char_start,
+        char_end,
+    })
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct DocumentArgs {
+    path: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProposeEditArgs {
+    path: Option<String>,
+    start: usize,
+    end: usize,
+    text: String,
+    /// Text the range is expected to cover, checked before recording.
+    original: Option<String>,
+    explanation: Option<String>,
+}
+
+#[derive(Serialize)]
+struct DocumentSummary<'a> {
+    path: &'a str,
+    title: &'a str,
+    active: bool,
+    chars: usize,
+    has_selection: bool,
+}
+
+fn tool_definitions() -> Value {
+    let path = json!({
+        "type": "string",
+        "description": "Path of the document, as listed by list_workspace_docs. Defaults to the active document."
+    });
+    json!([
+        {
+            "name": "get_document",
+            "description": "Full text of a document open in Clareza. Offsets used by the other tools count characters in this text.",
+            "inputSchema": { "type": "object", "properties": { "path": path } }
+        },
+        {
+            "name": "get_selection",
+            "description": "Text the user has selected in a document, with its offsets in UTF-16 code units (characters, except that emoji count as two).",
+            "inputSchema": { "type": "object", "properties": { "path": path } }
+        },
+        {
+            "name": "propose_edit",
+            "description": "Suggest replacing the UTF-16 code units [start, end) of a document with new text. The user reviews every proposal before it is applied; call it once per independent change.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "path": path,
+                    "start": { "type": "integer", "minimum": 0, "description": "Offset of the first replaced character." },
+                    "end": { "type": "integer", "minimum": 0, "description": "Offset after the last replaced character; equal to start to insert." },
+                    "text": { "type": "string", "description": "Replacement text." },
+                    "original": { "type": "string", "description": "Text currently in the range, to catch wrong offsets." },
+                    "explanation": { "type": "string", "description": "Short reason shown to the user." }
+                },
+                "required": ["start", "end", "text"]
+            }
+        },
+        {
+            "name": "list_workspace_docs",
+            "description": "Documents currently open in Clareza.",
+            "inputSchema": { "type": "object", "properties": {} }
+        }
+    ])
+}
+
+fn resolve<'a>(registry: &'a McpRegistry, path: Option<&str>) -> Result<&'a McpDocument, String> {
+    let path = match path.or(registry.active.as_deref()) {
+        Some(path) => path,
+        None => return Err("No document is open in Clareza".to_string()),
+    };
+    registry
+        .documents
+        .get(path)
+        .ok_or_else(|| format!("Document '{}' is not open in Clareza", path))
+}
+
+async fn get_document(args: DocumentArgs) -> Result<String, String> {
+    let registry = REGISTRY.read().await;
+    let document = resolve(&registry, args.path.as_deref())?;
+    Ok(json!({
+        "path": document.path,
+        "title": document.title,
+        "chars": utf16_len(&document.content),
+        "content": document.content,
+    })
+    .to_string())
+}
+
+async fn get_selection(args: DocumentArgs) -> Result<String, String> {
+    let registry = REGISTRY.read().await;
+    let document = resolve(&registry, args.path.as_deref())?;
+    let Some(range) = document.selection else {
+        return Err(format!("Nothing is selected in '{}'", document.path));
+    };
+    Ok(json!({
+        "path": document.path,
+        "start": range.char_start,
+        "end": range.char_end,
+        "text": &document.content[range.byte_start..range.byte_end],
+    })
+    .to_string())
+}
+
+async fn propose_edit(
+    state: &ServerState,
+    job_id: Option<String>,
+    args: ProposeEditArgs,
+) -> Result<String, String> {
+    let mut registry = REGISTRY.write().await;
+    let document = resolve(&registry, args.path.as_deref())?;
+    let range = range_for(&document.content, args.start, args.end).ok_or_else(|| {
+        format!(
+            "Range {}..{} is outside the document ({} characters)",
+            args.start,
+            args.end,
+            utf16_len(&document.content)
+        )
+    })?;
+    let original = document.content[range.byte_start..range.byte_end].to_string();
+    if args
+        .original
+        .as_ref()
+        .is_some_and(|expected| *expected != original)
+    {
+        return Err(format!(
+            "The range {}..{} contains {:?}, not the expected text. Read the document again and retry.",
+            args.start, args.end, original
+        ));
+    }
+
+    let edit = ProposedEdit {
+        id: Uuid::new_v4().to_string(),
+        job_id,
+        path: document.path.clone(),
+        range,
+        original,
+        replacement: args.text,
+        explanation: args.explanation,
+        created_at: Utc::now(),
+    };
+    println!(
+        "[MCP] Edit proposed for {} at {}..{}",
+        edit.path, range.char_start, range.char_end
+    );
+    (state.on_proposal)(&edit);
+    let id = edit.id.clone();
+    registry.proposals.push(edit);
+    Ok(json!({ "id": id, "status": "proposed" }).to_string())
+}
+
+async fn list_workspace_docs() -> Result<String, String> {
+    let registry = REGISTRY.read().await;
+    let mut documents: Vec<DocumentSummary> = registry
+        .documents
+        .values()
+        .map(|document| DocumentSummary {
+            path: &document.path,
+            title: &document.title,
+            active: registry.active.as_deref() == Some(document.path.as_str()),
+            chars: utf16_len(&document.content),
+            has_selection: document.selection.is_some(),
+        })
+        .collect();
+    documents.sort_by(|a, b| a.path.cmp(b.path));
+    serde_json::to_string(&documents).map_err(|e| e.to_string())
+}
+
+fn parse_args<T: for<'de> Deserialize<'de>>(arguments: Value) -> Result<T, (i64, String)> {
+    serde_json::from_value(arguments).map_err(|e| (-32602, format!("Invalid arguments: {}", e)))
+}
+
+async fn call_tool(
+    state: &ServerState,
+    job_id: Option<String>,
+    params: &Value,
+) -> Result<Value, (i64, String)> {
+    let name = params
+        .get("name")
+        .and_then(Value::as_str)
+        .ok_or((-32602, "Missing tool name".to_string()))?;
+    let arguments = params
+        .get("arguments")
+        .cloned()
+        .unwrap_or_else(|| json!({}));
+    println!("[MCP] Tool call: {}", name);
+
+    let outcome = match name {
+        "get_document" => get_document(parse_args(arguments)?).await,
+        "get_selection" => get_selection(parse_args(arguments)?).await,
+        "propose_edit" => propose_edit(state, job_id, parse_args(arguments)?).await,
+        "list_workspace_docs" => list_workspace_docs().await,
+        _ => return Err((-32602, format!("Unknown tool: {}", name))),
+    };
+    // Tool failures are results the model can read and react to
+    let (text, is_error) = match outcome {
+        Ok(text) => (text, false),
+        Err(message) => (message, true),
+    };
+    Ok(json!({
+        "content": [{ "type": "text", "text": text }],
+        "isError": is_error,
+    }))
+}
+
+fn rpc_error(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+/// Handle one JSON-RPC message. Notifications and responses get no reply.
+async fn handle_message(
+    state: &ServerState,
+    job_id: Option<String>,
+    message: Value,
+) -> Option<Value> {
+    let id = message.get("id").cloned();
+    let Some(method) = message.get("method").and_then(Value::as_str) else {
+        return id.map(|id| rpc_error(id, -32600, "Invalid request"));
+    };
+    let id = id?;
+    let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+    let result = match method {
+        "initialize" => {
+            let requested = params.get("protocolVersion").and_then(Value::as_str);
+            let version = requested
+                .filter(|v| SUPPORTED_VERSIONS.contains(v))
+                .unwrap_or(PROTOCOL_VERSION);
+            Ok(json!({
+                "protocolVersion": version,
+                "capabilities": { "tools": { "listChanged": false } },
+                "serverInfo": { "name": MCP_SERVER_NAME, "version": env!("CARGO_PKG_VERSION") },
+            }))
+        }
+        "ping" => Ok(json!({})),
+        "tools/list" => Ok(json!({ "tools": tool_definitions() })),
+        "tools/call" => call_tool(state, job_id, &params).await,
+        _ => Err((-32601, format!("Method not found: {}", method))),
+    };
+
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err((code, message)) => rpc_error(id, code, &message),
+    })
+}
+
+/// Only local clients holding the token may talk to the server. Browsers
+/// always send `Origin`, which keeps web pages from reaching it.
+fn reject(state: &ServerState, headers: &HeaderMap) -> Option<Response> {
+    let authorized = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .is_some_and(|token| token == state.token);
+    if !authorized {
+        return Some(StatusCode::UNAUTHORIZED.into_response());
+    }
+    if headers.contains_key(header::ORIGIN) {
+        return Some(StatusCode::FORBIDDEN.into_response());
+    }
+    None
+}
+
+async fn handle_post(
+    State(state): State<ServerState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    if let Some(rejection) = reject(&state, &headers) {
+        return rejection;
+    }
+    let job_id = headers
+        .get(JOB_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+
+    let message: Value = match serde_json::from_slice(&body) {
+        Ok(message) => message,
+        Err(e) => {
+            let error = rpc_error(Value::Null, -32700, &format!("Parse error: {}", e));
+            return (StatusCode::BAD_REQUEST, Json(error)).into_response();
+        }
+    };
+
+    let reply = match message {
+        Value::Array(messages) => {
+            let mut replies = Vec::new();
+            for message in messages {
+                replies.extend(handle_message(&state, job_id.clone(), message).await);
+            }
+            (!replies.is_empty()).then_some(Value::Array(replies))
+        }
+        message => handle_message(&state, job_id, message).await,
+    };
+    match reply {
+        Some(reply) => Json(reply).into_response(),
+        None => StatusCode::ACCEPTED.into_response(),
+    }
+}
+
+/// No server-initiated messages, so no event stream to open.
+async fn method_not_allowed() -> StatusCode {
+    StatusCode::METHOD_NOT_ALLOWED
+}
+
+/// Start the MCP server on a random localhost port, or return the running
+/// one. While it runs, Gemini CLI jobs are configured to use it.
+#[tauri::command]
+pub async fn start_mcp_server(app_handle: AppHandle) -> Result<McpServerInfo, ClarezaError> {
+    let mut server = SERVER.lock().await;
+    if let Some(running) = server.as_ref() {
+        return Ok(running.info.clone());
+    }
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let info = McpServerInfo {
+        url: format!("http://{}/mcp", listener.local_addr()?),
+        token: Uuid::new_v4().simple().to_string(),
+    };
+    let state = ServerState {
+        token: info.token.clone(),
+        on_proposal: Arc::new(move |edit| {
+            if let Err(e) = app_handle.emit("mcp-edit-proposed", edit.clone()) {
+                println!("[MCP] Failed to emit proposal: {:?}", e);
+            }
+        }),
+    };
+    let router = Router::new()
+        .route(
+            "/mcp",
+            post(handle_post)
+                .get(method_not_allowed)
+                .delete(method_not_allowed),
+        )
+        .with_state(state);
+
+    let (shutdown, stopped) = oneshot::channel::<()>();
+    tokio::spawn(async move {
+        let serve = axum::serve(listener, router).with_graceful_shutdown(async {
+            let _ = stopped.await;
+        });
+        if let Err(e) = serve.await {
+            println!("[MCP] Server error: {}", e);
+        }
+        println!("[MCP] Server stopped");
+    });
+
+    println!("[MCP] Server listening on {}", info.url);
+    *server = Some(RunningServer {
+        info: info.clone(),
+        shutdown,
+    });
+    Ok(info)
+}
+
+#[tauri::command]
+pub async fn stop_mcp_server() -> Result<(), ClarezaError> {
+    if let Some(running) = SERVER.lock().await.take() {
+        let _ = running.shutdown.send(());
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_mcp_server_info() -> Result<Option<McpServerInfo>, ClarezaError> {
+    Ok(server_info().await)
+}
+
+/// Make a document and its selection (UTF-16 offsets, as the editor reports
+/// them) visible to the MCP tools. Proposals whose range no longer matches
+/// the new content are dropped.
+#[tauri::command]
+pub async fn publish_mcp_document(
+    path: String,
+    title: String,
+    content: String,
+    selection_start: Option<usize>,
+    selection_end: Option<usize>,
+    active: Option<bool>,
+) -> Result<(), ClarezaError> {
+    let selection = match (selection_start, selection_end) {
+        (Some(start), Some(end)) if start < end => {
+            Some(range_for(&content, start, end).ok_or_else(|| {
+                ClarezaError::InvalidFormat("Selection is outside the document".to_string())
+            })?)
+        }
+        _ => None,
+    };
+
+    let mut registry = REGISTRY.write().await;
+    registry.proposals.retain(|edit| {
+        edit.path != path
+            || content.get(edit.range.byte_start..edit.range.byte_end)
+                == Some(edit.original.as_str())
+    });
+    if active.unwrap_or(true) {
+        registry.active = Some(path.clone());
+    }
+    registry.documents.insert(
+        path.clone(),
+        McpDocument {
+            path,
+            title,
+            content,
+            selection,
+            updated_at: Utc::now(),
+        },
+    );
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn close_mcp_document(path: String) -> Result<(), ClarezaError> {
+    let mut registry = REGISTRY.write().await;
+    registry.documents.remove(&path);
+    registry.proposals.retain(|edit| edit.path != path);
+    if registry.active.as_deref() == Some(path.as_str()) {
+        registry.active = None;
+    }
+    Ok(())
+}
+
+/// Pending proposals, oldest first, optionally for one document only.
+#[tauri::command]
+pub async fn list_proposed_edits(path: Option<String>) -> Result<Vec<ProposedEdit>, ClarezaError> {
+    let registry = REGISTRY.read().await;
+    Ok(registry
+        .proposals
+        .iter()
+        .filter(|edit| path.as_ref().is_none_or(|path| edit.path == *path))
+        .cloned()
+        .collect())
+}
+
+/// Forget proposals once the user has applied or rejected them. Returns how
+/// many were removed.
+#[tauri::command]
+pub async fn dismiss_proposed_edits(ids: Vec<String>) -> Result<usize, ClarezaError> {
+    let mut registry = REGISTRY.write().await;
+    let before = registry.proposals.len();
+    registry.proposals.retain(|edit| !ids.contains(&edit.id));
+    Ok(before - registry.proposals.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+    use std::sync::Mutex as StdMutex;
+
+    const TOKEN: &str = "test-token";
+
+    fn test_state() -> (ServerState, Arc<StdMutex<Vec<ProposedEdit>>>) {
+        let proposed = Arc::new(StdMutex::new(Vec::new()));
+        let sink = proposed.clone();
+        let state = ServerState {
+            token: TOKEN.to_string(),
+            on_proposal: Arc::new(move |edit| sink.lock().unwrap().push(edit.clone())),
+        };
+        (state, proposed)
+    }
+
+    fn headers(authorization: Option<&str>) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Some(value) = authorization {
+            headers.insert(header::AUTHORIZATION, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    fn authorized() -> HeaderMap {
+        headers(Some(&format!("Bearer {}", TOKEN)))
+    }
+
+    async fn post(state: &ServerState, headers: HeaderMap, body: Value) -> (StatusCode, Value) {
+        let response =
+            handle_post(State(state.clone()), headers, Bytes::from(body.to_string())).await;
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let reply = if body.is_empty() {
+            Value::Null
+        } else {
+            serde_json::from_slice(&body).unwrap()
+        };
+        (status, reply)
+    }
+
+    fn tool_call(name: &str, arguments: Value) -> Value {
+        json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "tools/call",
+            "params": { "name": name, "arguments": arguments },
+        })
+    }
+
+    /// The text of a tool result and whether it is an error.
+    fn tool_result(reply: &Value) -> (String, bool) {
+        let result = &reply["result"];
+        (
+            result["content"][0]["text"].as_str().unwrap().to_string(),
+            result["isError"].as_bool().unwrap(),
+        )
+    }
+
+    async fn publish(path: &str, content: &str) {
+        publish_mcp_document(
+            path.to_string(),
+            "Teste".to_string(),
+            content.to_string(),
+            None,
+            None,
+            Some(false),
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn rejects_requests_without_the_token() {
+        let (state, _) = test_state();
+        let ping = json!({ "jsonrpc": "2.0", "id": 1, "method": "ping" });
+        for authorization in [
+            None,
+            Some("Bearer wrong"),
+            Some(TOKEN),
+            Some("Basic test-token"),
+        ] {
+            let (status, _) = post(&state, headers(authorization), ping.clone()).await;
+            assert_eq!(status, StatusCode::UNAUTHORIZED, "{:?}", authorization);
+        }
+        let (status, reply) = post(&state, authorized(), ping).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(reply["result"], json!({}));
+    }
+
+    #[tokio::test]
+    async fn rejects_requests_from_browsers() {
+        let (state, _) = test_state();
+        let mut headers = authorized();
+        headers.insert(
+            header::ORIGIN,
+            HeaderValue::from_static("https://example.com"),
+        );
+        let (status, _) = post(
+            &state,
+            headers,
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "ping" }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn dispatches_json_rpc_messages() {
+        let (state, _) = test_state();
+        let (_, reply) = post(
+            &state,
+            authorized(),
+            json!({ "jsonrpc": "2.0", "id": 7, "method": "unknown/method" }),
+        )
+        .await;
+        assert_eq!(reply["id"], 7);
+        assert_eq!(reply["error"]["code"], -32601);
+
+        // Notifications get no reply
+        let (status, _) = post(
+            &state,
+            authorized(),
+            json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+
+        let (_, reply) = post(
+            &state,
+            authorized(),
+            json!([
+                { "jsonrpc": "2.0", "id": 1, "method": "ping" },
+                { "jsonrpc": "2.0", "id": 2, "method": "tools/list" },
+            ]),
+        )
+        .await;
+        let replies = reply.as_array().unwrap();
+        assert_eq!(replies.len(), 2);
+        assert!(replies[1]["result"]["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|tool| tool["name"] == "propose_edit"));
+    }
+
+    #[tokio::test]
+    async fn get_document_returns_the_published_content() {
+        let (state, _) = test_state();
+        let path = "/mcp-tests/get.md";
+        publish(path, "Olá 😀 mundo").await;
+
+        let (_, reply) = post(
+            &state,
+            authorized(),
+            tool_call("get_document", json!({ "path": path })),
+        )
+        .await;
+        let (text, is_error) = tool_result(&reply);
+        assert!(!is_error);
+        let document: Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(document["content"], "Olá 😀 mundo");
+        assert_eq!(document["chars"], 12);
+
+        let (_, reply) = post(
+            &state,
+            authorized(),
+            tool_call("get_document", json!({ "path": "/mcp-tests/missing.md" })),
+        )
+        .await;
+        assert!(tool_result(&reply).1);
+    }
+
+    #[tokio::test]
+    async fn propose_edit_is_scoped_to_the_calling_job() {
+        let (state, proposed) = test_state();
+        let path = "/mcp-tests/propose.md";
+        publish(path, "Olá 😀 mundo").await;
+
+        let mut headers = authorized();
+        headers.insert(JOB_HEADER, HeaderValue::from_static("job-1"));
+        let arguments =
+            json!({ "path": path, "start": 4, "end": 6, "text": "🙂", "original": "😀" });
+        let (_, reply) = post(&state, headers, tool_call("propose_edit", arguments)).await;
+        assert!(!tool_result(&reply).1);
+
+        let proposed = proposed.lock().unwrap().clone();
+        assert_eq!(proposed.len(), 1);
+        assert_eq!(proposed[0].job_id.as_deref(), Some("job-1"));
+        assert_eq!(proposed[0].original, "😀");
+        let listed = list_proposed_edits(Some(path.to_string())).await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].id, proposed[0].id);
+
+        // Refused when the range no longer holds the expected text; recorded
+        // without a job when the header is missing
+        let arguments =
+            json!({ "path": path, "start": 0, "end": 3, "text": "Oi", "original": "Ola" });
+        let (_, reply) = post(&state, authorized(), tool_call("propose_edit", arguments)).await;
+        assert!(tool_result(&reply).1);
+        let arguments = json!({ "path": path, "start": 0, "end": 3, "text": "Oi" });
+        let (_, reply) = post(&state, authorized(), tool_call("propose_edit", arguments)).await;
+        assert!(!tool_result(&reply).1);
+        let listed = list_proposed_edits(Some(path.to_string())).await.unwrap();
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed[1].job_id, None);
+    }
+}
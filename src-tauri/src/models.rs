@@ -30,6 +30,8 @@ pub struct FileOperation {
     pub path: Option<String>,
     pub content: Option<String>,
     pub metadata: Option<DocumentMetadata>,
+    /// Encoding the document is stored in, to be passed back when saving
+    pub encoding: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -47,6 +49,8 @@ pub struct RecentFile {
     pub title: String,
     pub last_opened: DateTime<Utc>,
     pub exists: bool,
+    #[serde(default)]
+    pub pinned: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
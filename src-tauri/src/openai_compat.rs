@@ -0,0 +1,286 @@
+// src-tauri/src/openai_compat.rs
+
+use async_trait::async_trait;
+use futures_util::StreamExt;
+use std::time::Duration;
+use tokio::time::timeout;
+
+use crate::engine::{EngineRequest, TextEngine};
+use crate::errors::AiError;
+use crate::jobs::JobReporter;
+
+/// Map a failed request to the error kind the UI explains.
+pub(crate) fn request_error(base_url: &str, e: reqwest::Error) -> AiError {
+    // Without the URL, its query string may carry credentials
+    let e = e.without_url();
+    let detail = format!("Failed to reach {}: {}", base_url, e);
+    if e.is_connect() {
+        AiError::NetworkUnreachable(detail)
+    } else {
+        AiError::classify(&detail)
+    }
+}
+
+/// Map an error response status to the error kind the UI explains.
+pub(crate) fn status_error(status: reqwest::StatusCode, body: &str) -> AiError {
+    let detail = format!("Provider returned {}: {}", status, body.trim());
+    match status.as_u16() {
+        401 | 403 => AiError::NotAuthenticated(detail),
+        404 => AiError::ModelUnavailable(detail),
+        413 => AiError::PromptTooLarge(detail),
+        429 => match AiError::classify(&detail) {
+            quota @ AiError::QuotaExceeded(_) => quota,
+            _ => AiError::RateLimited(detail),
+        },
+        _ => AiError::classify(&detail),
+    }
+}
+
+/// Remove the complete lines from `buffer` and return them decoded and
+/// trimmed. Raw bytes are kept until a line is complete, as a character can
+/// be split across network chunks.
+fn take_lines(buffer: &mut Vec<u8>) -> Vec<String> {
+    let mut lines = Vec::new();
+    while let Some(newline) = buffer.iter().position(|&b| b == b'\n') {
+        let line: Vec<u8> = buffer.drain(..=newline).collect();
+        lines.push(String::from_utf8_lossy(&line).trim().to_string());
+    }
+    lines
+}
+
+/// One server-sent event of a streamed completion.
+#[derive(Debug, PartialEq)]
+enum SseEvent {
+    Content(String),
+    /// Data that is not JSON, shown as is.
+    Other(String),
+    Error(AiError),
+    Done,
+}
+
+/// Events are `data: {...}` lines, `data: [DONE]` at the end. Other lines
+/// (comments, event names, chunks without text) give `None`.
+fn parse_event(line: &str) -> Option<SseEvent> {
+    let data = line.strip_prefix("data:")?.trim();
+    if data == "[DONE]" {
+        return Some(SseEvent::Done);
+    }
+    let Ok(json) = serde_json::from_str::<serde_json::Value>(data) else {
+        return Some(SseEvent::Other(data.to_string()));
+    };
+
+    if let Some(error) = json.get("error") {
+        let message = error
+            .get("message")
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+            .unwrap_or_else(|| error.to_string());
+        return Some(SseEvent::Error(AiError::classify(&message)));
+    }
+
+    json.pointer("/choices/0/delta/content")
+        .and_then(|v| v.as_str())
+        .map(|content| SseEvent::Content(content.to_string()))
+}
+
+/// Text engine talking to an OpenAI-compatible `/chat/completions` endpoint
+/// (Ollama, llama.cpp server, LM Studio, vLLM...).
+pub struct OpenAiCompatibleEngine {
+    base_url: String,
+    api_key: Option<String>,
+    client: reqwest::Client,
+}
+
+impl OpenAiCompatibleEngine {
+    pub fn new(base_url: &str, api_key: Option<String>) -> Result<Self, AiError> {
+        let base_url = base_url.trim().trim_end_matches('/').to_string();
+        if !base_url.starts_with("http://") && !base_url.starts_with("https://") {
+            return Err(AiError::Other(format!("Invalid provider URL '{}'", base_url)));
+        }
+
+        let client = reqwest::Client::builder()
+            .connect_timeout(Duration::from_secs(10))
+            .build()
+            .map_err(|e| AiError::Other(format!("Failed to create HTTP client: {}", e)))?;
+
+        Ok(Self {
+            base_url,
+            api_key: api_key.filter(|k| !k.trim().is_empty()),
+            client,
+        })
+    }
+
+    async fn stream_completion(
+        &self,
+        request: &EngineRequest,
+        reporter: JobReporter,
+    ) -> Result<String, AiError> {
+        let url = format!("{}/chat/completions", self.base_url);
+        println!("[OPENAI] POST {} (model: {})", url, request.model);
+
+        let body = serde_json::json!({
+            "model": request.model,
+            "stream": true,
+            "messages": [{ "role": "user", "content": request.prompt }],
+        });
+
+        let mut http_request = self.client.post(&url).json(&body);
+        if let Some(key) = &self.api_key {
+            http_request = http_request.bearer_auth(key);
+        }
+
+        let response = http_request
+            .send()
+            .await
+            .map_err(|e| request_error(&self.base_url, e))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            return Err(status_error(status, &text));
+        }
+
+        let mut accumulated = String::new();
+        let mut buffer: Vec<u8> = Vec::new();
+        let mut stream = response.bytes_stream();
+
+        // Server-sent events, see `parse_event`
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.map_err(|e| {
+                AiError::NetworkUnreachable(format!("Failed to read response stream: {}", e))
+            })?;
+            buffer.extend_from_slice(&chunk);
+
+            for line in take_lines(&mut buffer) {
+                match parse_event(&line) {
+                    Some(SseEvent::Content(content)) => {
+                        reporter.output(content.as_str(), "stdout");
+                        accumulated.push_str(&content);
+                    }
+                    Some(SseEvent::Other(data)) => reporter.output(data, "stdout"),
+                    Some(SseEvent::Error(error)) => return Err(error),
+                    Some(SseEvent::Done) => return Ok(accumulated),
+                    None => {}
+                }
+            }
+        }
+
+        Ok(accumulated)
+    }
+
+    /// Model ids served by the provider, from `GET /models`.
+    pub async fn list_models(&self) -> Result<Vec<String>, AiError> {
+        let url = format!("{}/models", self.base_url);
+        println!("[OPENAI] GET {}", url);
+
+        let mut http_request = self.client.get(&url).timeout(Duration::from_secs(15));
+        if let Some(key) = &self.api_key {
+            http_request = http_request.bearer_auth(key);
+        }
+        let response = http_request
+            .send()
+            .await
+            .map_err(|e| request_error(&self.base_url, e))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            return Err(status_error(status, &text));
+        }
+
+        let json: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| AiError::Other(format!("Invalid model list: {}", e)))?;
+        let mut models: Vec<String> = json["data"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|model| model["id"].as_str().map(str::to_string))
+            .collect();
+        models.sort();
+        Ok(models)
+    }
+}
+
+#[async_trait]
+impl TextEngine for OpenAiCompatibleEngine {
+    fn name(&self) -> &'static str {
+        "openai-compatible"
+    }
+
+    async fn generate(
+        &self,
+        request: &EngineRequest,
+        reporter: JobReporter,
+    ) -> Result<String, AiError> {
+        match timeout(
+            Duration::from_secs(request.timeout_secs),
+            self.stream_completion(request, reporter),
+        )
+        .await
+        {
+            Ok(result) => result,
+            Err(_) => Err(AiError::Timeout(request.timeout_secs)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_characters_split_across_chunks() {
+        let line = "data: {\"choices\":[{\"delta\":{\"content\":\"ação\"}}]}\n".as_bytes();
+        // Split inside the two bytes of 'ç'
+        let split = line.iter().position(|&b| b == 0xC3).unwrap() + 1;
+        let mut buffer = Vec::new();
+
+        buffer.extend_from_slice(&line[..split]);
+        assert!(take_lines(&mut buffer).is_empty());
+        buffer.extend_from_slice(&line[split..]);
+        let lines = take_lines(&mut buffer);
+        assert!(buffer.is_empty());
+
+        assert_eq!(lines.len(), 1);
+        assert_eq!(
+            parse_event(&lines[0]),
+            Some(SseEvent::Content("ação".to_string()))
+        );
+    }
+
+    #[test]
+    fn takes_every_complete_line() {
+        let mut buffer = b"data: um\r\n\ndata: dois\ndata: tr".to_vec();
+        assert_eq!(take_lines(&mut buffer), ["data: um", "", "data: dois"]);
+        assert_eq!(buffer, b"data: tr");
+    }
+
+    #[test]
+    fn parses_stream_events() {
+        assert_eq!(parse_event("data: [DONE]"), Some(SseEvent::Done));
+        assert_eq!(parse_event(": keep-alive"), None);
+        assert_eq!(parse_event(""), None);
+        assert_eq!(
+            parse_event("data: {\"choices\":[{\"delta\":{\"role\":\"assistant\"}}]}"),
+            None
+        );
+        assert_eq!(
+            parse_event("data: not json"),
+            Some(SseEvent::Other("not json".to_string()))
+        );
+    }
+
+    #[test]
+    fn parses_error_payloads() {
+        assert!(matches!(
+            parse_event("data: {\"error\":{\"message\":\"Rate limit reached, retry after 2s\"}}"),
+            Some(SseEvent::Error(AiError::RateLimited(_)))
+        ));
+        assert!(matches!(
+            parse_event("data: {\"error\":\"model 'llama9' not found\"}"),
+            Some(SseEvent::Error(AiError::ModelUnavailable(_)))
+        ));
+    }
+}
@@ -0,0 +1,112 @@
+// src-tauri/src/pipeline.rs
+
+use crate::cache::generate_cached;
+use crate::engine::{EngineRequest, TextEngine};
+use crate::errors::{AiError, ClarezaError};
+use crate::fencing::{check_response, strip_markers};
+use crate::jobs::JobReporter;
+use crate::redaction::{RedactionOptions, Redactor};
+use crate::retry::RetryPolicy;
+use crate::templates::TemplateVariables;
+
+/// A model answer ready to be shown: sensitive data restored and fence
+/// markers removed.
+#[derive(Debug, Clone)]
+pub struct CheckedResponse {
+    pub content: String,
+    pub injection_warning: Option<String>,
+}
+
+/// The steps every AI tool runs around the engine call: masking sensitive
+/// data in what is sent, then restoring, checking and cleaning the answer.
+///
+/// Without redaction options the text passes through unchanged.
+pub struct RequestPipeline {
+    redactor: Option<Redactor>,
+}
+
+impl RequestPipeline {
+    pub fn new(redaction: Option<&RedactionOptions>) -> Result<Self, ClarezaError> {
+        Ok(Self {
+            redactor: redaction.map(Redactor::new).transpose()?,
+        })
+    }
+
+    pub fn redact(&mut self, text: &str) -> String {
+        match &mut self.redactor {
+            Some(redactor) => redactor.redact(text),
+            None => text.to_string(),
+        }
+    }
+
+    pub fn redact_variables(&mut self, variables: &TemplateVariables) -> TemplateVariables {
+        match &mut self.redactor {
+            Some(redactor) => redactor.redact_variables(variables),
+            None => variables.clone(),
+        }
+    }
+
+    /// Whether the engine may read the open documents itself, which would
+    /// bypass the masking.
+    pub fn document_tools(&self) -> bool {
+        self.redactor.is_none()
+    }
+
+    /// Tell the user how much was masked, once the request is built.
+    pub fn report_masking(&self, reporter: &JobReporter) {
+        if let Some(redactor) = &self.redactor {
+            reporter.output(
+                format!("🔒 {} dados sensíveis mascarados antes do envio", redactor.masked_count()),
+                "system",
+            );
+        }
+    }
+
+    pub fn restore(&self, response: &str) -> String {
+        match &self.redactor {
+            Some(redactor) => redactor.restore(response),
+            None => response.to_string(),
+        }
+    }
+
+    /// Restore the masked data, warn about a likely prompt injection and
+    /// remove copied fence markers. `sources` and `rewritten` are the
+    /// unmasked texts, see `fencing::check_response`.
+    pub fn finish(
+        &self,
+        response: &str,
+        sources: &[&str],
+        rewritten: Option<&str>,
+        reporter: &JobReporter,
+    ) -> CheckedResponse {
+        let response = self.restore(response);
+        let injection_warning = check_response(sources, rewritten, &response);
+        if let Some(warning) = &injection_warning {
+            println!("[PIPELINE] Possible prompt injection: {}", warning);
+            reporter.output(
+                format!("⚠️ Atenção: {}. Revise o resultado antes de aplicá-lo.", warning),
+                "system",
+            );
+        }
+        CheckedResponse {
+            content: strip_markers(&response),
+            injection_warning,
+        }
+    }
+
+    /// Send a single request through the cache and `finish` the answer.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn generate(
+        &self,
+        engine: &dyn TextEngine,
+        request: &EngineRequest,
+        retry: &RetryPolicy,
+        bypass_cache: bool,
+        sources: &[&str],
+        rewritten: Option<&str>,
+        reporter: &JobReporter,
+    ) -> Result<CheckedResponse, AiError> {
+        let response = generate_cached(engine, request, retry, bypass_cache, reporter).await?;
+        Ok(self.finish(&response, sources, rewritten, reporter))
+    }
+}
@@ -0,0 +1,281 @@
+// src-tauri/src/prompts.rs
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::errors::ClarezaError;
+use crate::templates::{Template, Variable};
+use crate::utils::FileUtils;
+
+const LIBRARY_FILE: &str = "prompt_tools.json";
+const LIBRARY_VERSION: u32 = 1;
+
+/// Legacy marker replaced by the whole document, like `{{document}}`.
+pub const FILE_REFERENCE: &str = "@file_reference";
+
+/// Serializes read-modify-write cycles on the library file.
+static LIBRARY_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputMode {
+    /// The response replaces the document or the selection.
+    #[default]
+    Replace,
+    /// The response is shown next to the text, which stays untouched.
+    Analysis,
+}
+
+/// A user-defined tool, as stored in the library.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptTool {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub template: String,
+    pub target_language: String,
+    pub output_mode: OutputMode,
+    pub created_at: DateTime<Utc>,
+    pub modified_at: DateTime<Utc>,
+}
+
+/// Fields the user edits when creating or updating a tool.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PromptToolInput {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub template: String,
+    #[serde(default = "default_language")]
+    pub target_language: String,
+    #[serde(default)]
+    pub output_mode: OutputMode,
+}
+
+fn default_language() -> String {
+    "pt-BR".to_string()
+}
+
+/// On-disk format, also used for import and export.
+#[derive(Debug, Serialize, Deserialize)]
+struct PromptLibrary {
+    version: u32,
+    tools: Vec<PromptTool>,
+}
+
+/// A tool needs a name and a template that says where the text goes, or the
+/// model never sees it.
+fn validate_tool(name: &str, template: &str) -> Result<(), ClarezaError> {
+    if name.trim().is_empty() {
+        return Err(ClarezaError::InvalidFormat(
+            "Tool name cannot be empty".to_string(),
+        ));
+    }
+    // Parsed, so `{{ selection }}` and placeholders inside conditions count too
+    let parsed = Template::parse(template)?;
+    if !template.contains(FILE_REFERENCE)
+        && !parsed.uses(Variable::Document)
+        && !parsed.uses(Variable::Selection)
+    {
+        return Err(ClarezaError::InvalidFormat(format!(
+            "Template of '{}' must contain {}, {{{{document}}}} or {{{{selection}}}}",
+            name.trim(),
+            FILE_REFERENCE
+        )));
+    }
+    Ok(())
+}
+
+fn library_path(app_handle: &AppHandle) -> Result<PathBuf, ClarezaError> {
+    app_handle
+        .path()
+        .app_config_dir()
+        .map(|dir| dir.join(LIBRARY_FILE))
+        .map_err(|e| ClarezaError::Path(format!("Cannot determine config directory: {}", e)))
+}
+
+async fn load_library(app_handle: &AppHandle) -> Result<Vec<PromptTool>, ClarezaError> {
+    let path = library_path(app_handle)?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = FileUtils::read_with_encoding(&path).await?;
+    let library: PromptLibrary = serde_json::from_str(&content)?;
+    Ok(library.tools)
+}
+
+async fn store_library(app_handle: &AppHandle, tools: Vec<PromptTool>) -> Result<(), ClarezaError> {
+    let path = library_path(app_handle)?;
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let library = PromptLibrary {
+        version: LIBRARY_VERSION,
+        tools,
+    };
+    FileUtils::atomic_write(&path, &serde_json::to_string_pretty(&library)?).await
+}
+
+#[tauri::command]
+pub async fn list_prompt_tools(app_handle: AppHandle) -> Result<Vec<PromptTool>, ClarezaError> {
+    let _guard = LIBRARY_LOCK.lock().await;
+    load_library(&app_handle).await
+}
+
+#[tauri::command]
+pub async fn create_prompt_tool(
+    app_handle: AppHandle,
+    tool: PromptToolInput,
+) -> Result<PromptTool, ClarezaError> {
+    validate_tool(&tool.name, &tool.template)?;
+
+    let _guard = LIBRARY_LOCK.lock().await;
+    let mut tools = load_library(&app_handle).await?;
+    let now = Utc::now();
+    let created = PromptTool {
+        id: Uuid::new_v4().to_string(),
+        name: tool.name.trim().to_string(),
+        description: tool.description,
+        template: tool.template,
+        target_language: tool.target_language,
+        output_mode: tool.output_mode,
+        created_at: now,
+        modified_at: now,
+    };
+    tools.push(created.clone());
+    store_library(&app_handle, tools).await?;
+
+    println!("[PROMPTS] Created tool '{}' ({})", created.name, created.id);
+    Ok(created)
+}
+
+#[tauri::command]
+pub async fn update_prompt_tool(
+    app_handle: AppHandle,
+    id: String,
+    tool: PromptToolInput,
+) -> Result<PromptTool, ClarezaError> {
+    validate_tool(&tool.name, &tool.template)?;
+
+    let _guard = LIBRARY_LOCK.lock().await;
+    let mut tools = load_library(&app_handle).await?;
+    let existing = tools
+        .iter_mut()
+        .find(|t| t.id == id)
+        .ok_or_else(|| ClarezaError::FileNotFound(format!("Prompt tool {}", id)))?;
+
+    existing.name = tool.name.trim().to_string();
+    existing.description = tool.description;
+    existing.template = tool.template;
+    existing.target_language = tool.target_language;
+    existing.output_mode = tool.output_mode;
+    existing.modified_at = Utc::now();
+    let updated = existing.clone();
+    store_library(&app_handle, tools).await?;
+
+    println!("[PROMPTS] Updated tool '{}' ({})", updated.name, updated.id);
+    Ok(updated)
+}
+
+#[tauri::command]
+pub async fn delete_prompt_tool(app_handle: AppHandle, id: String) -> Result<(), ClarezaError> {
+    let _guard = LIBRARY_LOCK.lock().await;
+    let mut tools = load_library(&app_handle).await?;
+    let before = tools.len();
+    tools.retain(|t| t.id != id);
+    if tools.len() == before {
+        return Err(ClarezaError::FileNotFound(format!("Prompt tool {}", id)));
+    }
+    store_library(&app_handle, tools).await?;
+
+    println!("[PROMPTS] Deleted tool {}", id);
+    Ok(())
+}
+
+/// Write the whole library to `path` in the same format it is stored in.
+#[tauri::command]
+pub async fn export_prompt_tools(
+    app_handle: AppHandle,
+    path: String,
+) -> Result<usize, ClarezaError> {
+    let _guard = LIBRARY_LOCK.lock().await;
+    let tools = load_library(&app_handle).await?;
+    let count = tools.len();
+    let library = PromptLibrary {
+        version: LIBRARY_VERSION,
+        tools,
+    };
+    FileUtils::atomic_write(&path, &serde_json::to_string_pretty(&library)?).await?;
+
+    println!("[PROMPTS] Exported {} tools to {}", count, path);
+    Ok(count)
+}
+
+/// Add the tools from an exported file to the library.
+///
+/// Every tool is validated before anything is written, so a bad file leaves
+/// the library untouched. Tools whose id already exists are replaced when
+/// `overwrite` is set and imported as copies with a new id otherwise.
+#[tauri::command]
+pub async fn import_prompt_tools(
+    app_handle: AppHandle,
+    path: String,
+    overwrite: Option<bool>,
+) -> Result<Vec<PromptTool>, ClarezaError> {
+    let content = FileUtils::read_with_encoding(&path).await?;
+    let imported: PromptLibrary = serde_json::from_str(&content)?;
+    if imported.version > LIBRARY_VERSION {
+        return Err(ClarezaError::InvalidFormat(format!(
+            "Unsupported prompt library version {}",
+            imported.version
+        )));
+    }
+    for tool in &imported.tools {
+        validate_tool(&tool.name, &tool.template)?;
+    }
+
+    let _guard = LIBRARY_LOCK.lock().await;
+    let mut tools = load_library(&app_handle).await?;
+    let overwrite = overwrite.unwrap_or(false);
+    let mut added = Vec::with_capacity(imported.tools.len());
+    for mut tool in imported.tools {
+        match tools.iter_mut().find(|t| t.id == tool.id) {
+            Some(existing) if overwrite => *existing = tool.clone(),
+            Some(_) => {
+                tool.id = Uuid::new_v4().to_string();
+                tools.push(tool.clone());
+            }
+            None => tools.push(tool.clone()),
+        }
+        added.push(tool);
+    }
+    store_library(&app_handle, tools).await?;
+
+    println!("[PROMPTS] Imported {} tools from {}", added.len(), path);
+    Ok(added)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_any_placeholder_for_the_text() {
+        assert!(validate_tool("Revisar", "Revise: @file_reference").is_ok());
+        assert!(validate_tool("Revisar", "Revise: {{document}}").is_ok());
+        assert!(validate_tool("Revisar", "Revise: {{ selection }}").is_ok());
+        assert!(validate_tool("Revisar", "{{#if tone}}{{document}}{{/if}}").is_ok());
+    }
+
+    #[test]
+    fn rejects_tools_that_never_see_the_text() {
+        assert!(validate_tool("Revisar", "Revise o texto em {{language}}").is_err());
+        assert!(validate_tool("  ", "{{document}}").is_err());
+        assert!(validate_tool("Revisar", "{{#if tone}}{{document}}").is_err());
+    }
+}
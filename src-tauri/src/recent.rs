@@ -0,0 +1,251 @@
+// src-tauri/src/recent.rs
+
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tauri::AppHandle;
+use tokio::sync::Mutex;
+
+use crate::errors::ClarezaError;
+use crate::models::RecentFile;
+use crate::utils::FileUtils;
+
+const RECENT_FILE: &str = "recent_files.json";
+const DEFAULT_MAX_ENTRIES: usize = 15;
+const MAX_ENTRIES_LIMIT: usize = 100;
+
+/// Serializes read-modify-write cycles on the store.
+static RECENT_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+/// Most recently used first. Pinned entries are never evicted and do not
+/// count towards `max_entries`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RecentStore {
+    version: u32,
+    max_entries: usize,
+    files: Vec<RecentFile>,
+}
+
+impl Default for RecentStore {
+    fn default() -> Self {
+        Self {
+            version: 1,
+            max_entries: DEFAULT_MAX_ENTRIES,
+            files: Vec::new(),
+        }
+    }
+}
+
+impl RecentStore {
+    fn evict(&mut self) {
+        // The file may have been edited by hand
+        self.max_entries = self.max_entries.clamp(1, MAX_ENTRIES_LIMIT);
+        let mut unpinned = 0;
+        let max_entries = self.max_entries;
+        self.files.retain(|file| {
+            if file.pinned {
+                return true;
+            }
+            unpinned += 1;
+            unpinned <= max_entries
+        });
+    }
+}
+
+async fn load_store(app_handle: &AppHandle) -> Result<RecentStore, ClarezaError> {
+    let path = FileUtils::app_data_path(app_handle, RECENT_FILE).await?;
+    if !path.exists() {
+        return Ok(RecentStore::default());
+    }
+    let content = FileUtils::read_with_encoding(&path).await?;
+    match serde_json::from_str(&content) {
+        Ok(store) => Ok(store),
+        Err(e) => {
+            // Start over rather than losing the list for good, keeping the
+            // bad file aside in case it is worth recovering by hand
+            println!("[RECENT] Unreadable {}: {}", path.display(), e);
+            let backup = FileUtils::create_backup_path(&path)?;
+            tokio::fs::rename(&path, &backup).await?;
+            println!("[RECENT] Moved it to {}", backup.display());
+            Ok(RecentStore::default())
+        }
+    }
+}
+
+async fn save_store(app_handle: &AppHandle, store: &RecentStore) -> Result<(), ClarezaError> {
+    let path = FileUtils::app_data_path(app_handle, RECENT_FILE).await?;
+    FileUtils::atomic_write(&path, &serde_json::to_string_pretty(store)?).await
+}
+
+async fn update_store(
+    app_handle: &AppHandle,
+    update: impl FnOnce(&mut RecentStore),
+) -> Result<RecentStore, ClarezaError> {
+    let _guard = RECENT_LOCK.lock().await;
+    let mut store = load_store(app_handle).await?;
+    update(&mut store);
+    store.evict();
+    save_store(app_handle, &store).await?;
+    Ok(store)
+}
+
+/// Move `path` to the top of the list. Called after a document is opened or
+/// saved; failures are logged only, they must never fail the file operation.
+pub async fn record_recent_file(app_handle: &AppHandle, path: &Path, title: &str) {
+    // Canonical when possible, so the same file is not listed twice
+    let path = FileUtils::safe_canonicalize(path)
+        .unwrap_or_else(|_| path.to_path_buf())
+        .to_string_lossy()
+        .to_string();
+    let title = title.to_string();
+
+    let result = update_store(app_handle, |store| {
+        let pinned = store
+            .files
+            .iter()
+            .any(|file| file.path == path && file.pinned);
+        store.files.retain(|file| file.path != path);
+        store.files.insert(
+            0,
+            RecentFile {
+                path,
+                title,
+                last_opened: Utc::now(),
+                exists: true,
+                pinned,
+            },
+        );
+    })
+    .await;
+    if let Err(e) = result {
+        println!("[RECENT] Could not record recent file: {}", e);
+    }
+}
+
+/// Pinned files first, then the rest by last use. `exists` is checked again
+/// on every call.
+#[tauri::command]
+pub async fn get_recent_files(app_handle: AppHandle) -> Result<Vec<RecentFile>, ClarezaError> {
+    let store = {
+        let _guard = RECENT_LOCK.lock().await;
+        load_store(&app_handle).await?
+    };
+
+    let mut files = store.files;
+    for file in &mut files {
+        file.exists = Path::new(&file.path).exists();
+    }
+    files.sort_by_key(|file| (!file.pinned, std::cmp::Reverse(file.last_opened)));
+    Ok(files)
+}
+
+#[tauri::command]
+pub async fn pin_recent_file(
+    app_handle: AppHandle,
+    path: String,
+    pinned: bool,
+) -> Result<(), ClarezaError> {
+    let mut found = false;
+    update_store(&app_handle, |store| {
+        if let Some(file) = store.files.iter_mut().find(|file| file.path == path) {
+            file.pinned = pinned;
+            found = true;
+        }
+    })
+    .await?;
+    if !found {
+        return Err(ClarezaError::FileNotFound(format!("Recent file {}", path)));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn remove_recent_file(app_handle: AppHandle, path: String) -> Result<(), ClarezaError> {
+    update_store(&app_handle, |store| {
+        store.files.retain(|file| file.path != path);
+    })
+    .await?;
+    Ok(())
+}
+
+/// Forget every entry, pinned ones included.
+#[tauri::command]
+pub async fn clear_recent_files(app_handle: AppHandle) -> Result<(), ClarezaError> {
+    update_store(&app_handle, |store| store.files.clear()).await?;
+    println!("[RECENT] List cleared");
+    Ok(())
+}
+
+/// How many unpinned files to remember, between 1 and 100.
+#[tauri::command]
+pub async fn set_recent_files_limit(
+    app_handle: AppHandle,
+    max_entries: usize,
+) -> Result<(), ClarezaError> {
+    if !(1..=MAX_ENTRIES_LIMIT).contains(&max_entries) {
+        return Err(ClarezaError::InvalidFormat(format!(
+            "Recent files limit must be between 1 and {}",
+            MAX_ENTRIES_LIMIT
+        )));
+    }
+    update_store(&app_handle, |store| store.max_entries = max_entries).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str, pinned: bool) -> RecentFile {
+        RecentFile {
+            path: path.to_string(),
+            title: path.to_string(),
+            last_opened: Utc::now(),
+            exists: true,
+            pinned,
+        }
+    }
+
+    fn paths(store: &RecentStore) -> Vec<&str> {
+        store.files.iter().map(|file| file.path.as_str()).collect()
+    }
+
+    #[test]
+    fn evict_keeps_pinned_entries() {
+        let mut store = RecentStore {
+            max_entries: 2,
+            files: vec![
+                file("a", false),
+                file("b", true),
+                file("c", false),
+                file("d", false),
+                file("e", true),
+            ],
+            ..Default::default()
+        };
+        store.evict();
+        assert_eq!(paths(&store), ["a", "b", "c", "e"]);
+    }
+
+    #[test]
+    fn evict_clamps_the_limit() {
+        let mut store = RecentStore {
+            max_entries: 0,
+            files: vec![file("a", false), file("b", false)],
+            ..Default::default()
+        };
+        store.evict();
+        assert_eq!(store.max_entries, 1);
+        assert_eq!(paths(&store), ["a"]);
+
+        let mut store = RecentStore {
+            max_entries: 10_000,
+            files: (0..150).map(|i| file(&i.to_string(), false)).collect(),
+            ..Default::default()
+        };
+        store.evict();
+        assert_eq!(store.max_entries, MAX_ENTRIES_LIMIT);
+        assert_eq!(store.files.len(), MAX_ENTRIES_LIMIT);
+    }
+}
@@ -0,0 +1,304 @@
+// src-tauri/src/redaction.rs
+
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use crate::errors::ClarezaError;
+use crate::templates::{GlossaryEntry, TemplateVariables};
+
+/// Which kinds of data to mask. Every built-in detector is on by default.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RedactionOptions {
+    pub cpf: bool,
+    pub cnpj: bool,
+    pub emails: bool,
+    pub phones: bool,
+    /// People or company names to mask, matched as whole words ignoring case.
+    pub names: Vec<String>,
+    /// Extra regular expressions, e.g. internal contract numbers.
+    pub patterns: Vec<String>,
+}
+
+impl Default for RedactionOptions {
+    fn default() -> Self {
+        Self {
+            cpf: true,
+            cnpj: true,
+            emails: true,
+            phones: true,
+            names: Vec::new(),
+            patterns: Vec::new(),
+        }
+    }
+}
+
+fn digits(text: &str) -> Vec<u32> {
+    text.chars().filter_map(|c| c.to_digit(10)).collect()
+}
+
+/// Check digit of the modulo 11 scheme shared by CPF and CNPJ.
+fn check_digit(digits: &[u32], weights: &[u32]) -> u32 {
+    let sum: u32 = digits.iter().zip(weights).map(|(d, w)| d * w).sum();
+    match sum % 11 {
+        0 | 1 => 0,
+        rest => 11 - rest,
+    }
+}
+
+fn is_valid_cpf(text: &str) -> bool {
+    let d = digits(text);
+    if d.len() != 11 || d.iter().all(|&x| x == d[0]) {
+        return false;
+    }
+    check_digit(&d[..9], &[10, 9, 8, 7, 6, 5, 4, 3, 2]) == d[9]
+        && check_digit(&d[..10], &[11, 10, 9, 8, 7, 6, 5, 4, 3, 2]) == d[10]
+}
+
+fn is_valid_cnpj(text: &str) -> bool {
+    let d = digits(text);
+    if d.len() != 14 || d.iter().all(|&x| x == d[0]) {
+        return false;
+    }
+    check_digit(&d[..12], &[5, 4, 3, 2, 9, 8, 7, 6, 5, 4, 3, 2]) == d[12]
+        && check_digit(&d[..13], &[6, 5, 4, 3, 2, 9, 8, 7, 6, 5, 4, 3, 2]) == d[13]
+}
+
+struct Detector {
+    label: &'static str,
+    regex: Regex,
+    /// Rejects matches that only look like the data, e.g. bad check digits.
+    validate: Option<fn(&str) -> bool>,
+}
+
+fn compile(pattern: &str) -> Result<Regex, ClarezaError> {
+    Regex::new(pattern)
+        .map_err(|e| ClarezaError::InvalidFormat(format!("Invalid pattern '{}': {}", pattern, e)))
+}
+
+/// Replaces sensitive data with placeholders like `[[CPF_1]]` and puts the
+/// originals back in the response.
+///
+/// The same value always gets the same placeholder within one redactor, so
+/// every chunk and context block of a request refers to it consistently.
+pub struct Redactor {
+    detectors: Vec<Detector>,
+    placeholders: HashMap<String, String>,
+    originals: HashMap<String, String>,
+    counters: HashMap<&'static str, usize>,
+    placeholder_regex: Regex,
+}
+
+impl Redactor {
+    pub fn new(options: &RedactionOptions) -> Result<Self, ClarezaError> {
+        // Earlier detectors win when matches of the same span overlap
+        let mut detectors = Vec::new();
+        if options.cnpj {
+            detectors.push(Detector {
+                label: "CNPJ",
+                regex: compile(r"\b\d{2}\.?\d{3}\.?\d{3}/?\d{4}-?\d{2}\b")?,
+                validate: Some(is_valid_cnpj),
+            });
+        }
+        if options.cpf {
+            detectors.push(Detector {
+                label: "CPF",
+                regex: compile(r"\b\d{3}\.?\d{3}\.?\d{3}-?\d{2}\b")?,
+                validate: Some(is_valid_cpf),
+            });
+        }
+        if options.emails {
+            detectors.push(Detector {
+                label: "EMAIL",
+                regex: compile(r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}\b")?,
+                validate: None,
+            });
+        }
+        if options.phones {
+            detectors.push(Detector {
+                label: "TELEFONE",
+                regex: compile(r"(?:\+55\s?)?(?:\(\d{2}\)|\b\d{2})\s?9?\s?\d{4}[-\s]?\d{4}\b")?,
+                validate: None,
+            });
+        }
+
+        let mut names: Vec<&str> = options
+            .names
+            .iter()
+            .map(|name| name.trim())
+            .filter(|name| !name.is_empty())
+            .collect();
+        if !names.is_empty() {
+            // Longest first so "Ana Maria" is masked whole rather than as "Ana"
+            names.sort_by_key(|name| std::cmp::Reverse(name.len()));
+            let alternatives: Vec<String> = names.iter().map(|n| regex::escape(n)).collect();
+            let regex = RegexBuilder::new(&format!(r"\b(?:{})\b", alternatives.join("|")))
+                .case_insensitive(true)
+                .build()
+                .map_err(|e| ClarezaError::InvalidFormat(format!("Invalid name list: {}", e)))?;
+            detectors.push(Detector {
+                label: "NOME",
+                regex,
+                validate: None,
+            });
+        }
+
+        for pattern in options.patterns.iter().filter(|p| !p.trim().is_empty()) {
+            detectors.push(Detector {
+                label: "DADO",
+                regex: compile(pattern)?,
+                validate: None,
+            });
+        }
+
+        Ok(Self {
+            detectors,
+            placeholders: HashMap::new(),
+            originals: HashMap::new(),
+            counters: HashMap::new(),
+            placeholder_regex: compile(r"\[\[[A-Z]+_\d+\]\]")?,
+        })
+    }
+
+    /// How many distinct values have been masked so far.
+    pub fn masked_count(&self) -> usize {
+        self.placeholders.len()
+    }
+
+    fn placeholder_for(&mut self, label: &'static str, original: &str) -> String {
+        if let Some(placeholder) = self.placeholders.get(original) {
+            return placeholder.clone();
+        }
+        let counter = self.counters.entry(label).or_insert(0);
+        *counter += 1;
+        let placeholder = format!("[[{}_{}]]", label, counter);
+        self.placeholders
+            .insert(original.to_string(), placeholder.clone());
+        self.originals
+            .insert(placeholder.clone(), original.to_string());
+        placeholder
+    }
+
+    pub fn redact(&mut self, text: &str) -> String {
+        // (start, end, detector index), longest match first at each position
+        let mut matches: Vec<(usize, usize, usize)> = Vec::new();
+        for (idx, detector) in self.detectors.iter().enumerate() {
+            for m in detector.regex.find_iter(text) {
+                if m.is_empty() || detector.validate.is_some_and(|valid| !valid(m.as_str())) {
+                    continue;
+                }
+                matches.push((m.start(), m.end(), idx));
+            }
+        }
+        matches.sort_by_key(|&(start, end, idx)| (start, std::cmp::Reverse(end), idx));
+
+        let mut result = String::with_capacity(text.len());
+        let mut last = 0;
+        for (start, end, idx) in matches {
+            if start < last {
+                continue;
+            }
+            let label = self.detectors[idx].label;
+            result.push_str(&text[last..start]);
+            result.push_str(&self.placeholder_for(label, &text[start..end]));
+            last = end;
+        }
+        result.push_str(&text[last..]);
+        result
+    }
+
+    /// Mask the template variables that can carry user text. `language` and
+    /// `tone` are tool parameters and are left alone.
+    pub fn redact_variables(&mut self, variables: &TemplateVariables) -> TemplateVariables {
+        TemplateVariables {
+            selection: variables.selection.as_deref().map(|v| self.redact(v)),
+            document: variables.document.as_deref().map(|v| self.redact(v)),
+            title: variables.title.as_deref().map(|v| self.redact(v)),
+            language: variables.language.clone(),
+            tone: variables.tone.clone(),
+            tags: variables.tags.iter().map(|tag| self.redact(tag)).collect(),
+            glossary: variables
+                .glossary
+                .iter()
+                .map(|entry| GlossaryEntry {
+                    term: self.redact(&entry.term),
+                    definition: self.redact(&entry.definition),
+                })
+                .collect(),
+        }
+    }
+
+    /// Put the original values back. Placeholders the model invented are
+    /// left as they are.
+    pub fn restore(&self, text: &str) -> String {
+        self.placeholder_regex
+            .replace_all(text, |caps: &regex::Captures| {
+                let placeholder = &caps[0];
+                self.originals
+                    .get(placeholder)
+                    .cloned()
+                    .unwrap_or_else(|| placeholder.to_string())
+            })
+            .into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_cpf_check_digits() {
+        assert!(is_valid_cpf("529.982.247-25"));
+        assert!(is_valid_cpf("52998224725"));
+        assert!(!is_valid_cpf("529.982.247-24"));
+        assert!(!is_valid_cpf("111.111.111-11"));
+        assert!(!is_valid_cpf("5299822472"));
+    }
+
+    #[test]
+    fn validates_cnpj_check_digits() {
+        assert!(is_valid_cnpj("11.222.333/0001-81"));
+        assert!(is_valid_cnpj("11222333000181"));
+        assert!(!is_valid_cnpj("11.222.333/0001-80"));
+        assert!(!is_valid_cnpj("00.000.000/0000-00"));
+    }
+
+    #[test]
+    fn masks_and_restores_sensitive_data() {
+        let mut redactor = Redactor::new(&RedactionOptions {
+            names: vec!["Ana Maria".to_string(), "Ana".to_string()],
+            ..Default::default()
+        })
+        .unwrap();
+        let text = "Ana Maria (CPF 529.982.247-25, ana@exemplo.com.br) e a empresa \
+11.222.333/0001-81. Número 529.982.247-24 não é CPF. Ana Maria assina.";
+        let masked = redactor.redact(text);
+
+        assert!(!masked.contains("529.982.247-25"));
+        assert!(!masked.contains("ana@exemplo.com.br"));
+        assert!(!masked.contains("Ana Maria"));
+        assert!(masked.contains("529.982.247-24"));
+        assert!(masked.contains("[[CNPJ_1]]"));
+        // The same value keeps the same placeholder
+        assert_eq!(masked.matches("[[NOME_1]]").count(), 2);
+        assert_eq!(redactor.masked_count(), 4);
+        assert_eq!(redactor.restore(&masked), text);
+    }
+
+    #[test]
+    fn leaves_unknown_placeholders_alone() {
+        let redactor = Redactor::new(&RedactionOptions::default()).unwrap();
+        assert_eq!(redactor.restore("[[CPF_9]] fica"), "[[CPF_9]] fica");
+    }
+
+    #[test]
+    fn rejects_invalid_patterns() {
+        let options = RedactionOptions {
+            patterns: vec!["(".to_string()],
+            ..Default::default()
+        };
+        assert!(Redactor::new(&options).is_err());
+    }
+}
@@ -0,0 +1,155 @@
+// src-tauri/src/retry.rs
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+use crate::engine::{EngineRequest, TextEngine};
+use crate::errors::AiError;
+use crate::jobs::JobReporter;
+use crate::usage::record_usage;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Total attempts, including the first one.
+    pub max_attempts: u32,
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+    /// Switch to a cheaper model when the quota of the current one runs out.
+    pub fallback_on_quota: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay_ms: 2000,
+            max_delay_ms: 30_000,
+            fallback_on_quota: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Exponential backoff with "equal jitter": half the delay is fixed, the
+    /// other half random, so concurrent chunks do not retry in lockstep.
+    fn delay(&self, attempt: u32) -> Duration {
+        let exp = self
+            .base_delay_ms
+            .saturating_mul(1u64 << (attempt - 1).min(16))
+            .min(self.max_delay_ms);
+        let half = exp / 2;
+        Duration::from_millis(half + rand::thread_rng().gen_range(0..=half))
+    }
+}
+
+/// Cheaper model to fall back to when `model` is out of quota.
+fn quota_fallback(model: &str) -> Option<&'static str> {
+    match model {
+        "gemini-2.5-pro" => Some("gemini-2.5-flash"),
+        "gemini-2.5-flash" => Some("gemini-2.5-flash-lite"),
+        _ => None,
+    }
+}
+
+/// Run `request`, retrying transient failures according to `policy`.
+/// Returns the response and the model that produced it, which differs from
+/// the requested one after a quota fallback.
+pub async fn generate_with_retry(
+    engine: &dyn TextEngine,
+    request: &EngineRequest,
+    policy: &RetryPolicy,
+    reporter: &JobReporter,
+) -> Result<(String, String), AiError> {
+    let max_attempts = policy.max_attempts.max(1);
+    let mut request = request.clone();
+    let mut attempt = 1;
+
+    loop {
+        let started = Instant::now();
+        let result = engine.generate(&request, reporter.clone()).await;
+        record_usage(
+            reporter,
+            engine.name(),
+            &request.model,
+            &request.prompt,
+            result.as_deref(),
+            false,
+            started.elapsed().as_millis() as u64,
+        )
+        .await;
+
+        let error = match result {
+            Ok(content) => return Ok((content, request.model)),
+            Err(e) => e,
+        };
+
+        // An exhausted quota does not come back by waiting, but a cheaper
+        // model has its own. Not counted as an attempt: the chain is short.
+        if let AiError::QuotaExceeded(_) = error {
+            if let Some(fallback) =
+                quota_fallback(&request.model).filter(|_| policy.fallback_on_quota)
+            {
+                reporter.output(
+                    format!("↘ Cota de {} esgotada, usando {}", request.model, fallback),
+                    "system",
+                );
+                request.model = fallback.to_string();
+                continue;
+            }
+        }
+
+        if !error.is_transient() || attempt >= max_attempts {
+            return Err(error);
+        }
+
+        let delay = policy.delay(attempt);
+        println!(
+            "[RETRY] Attempt {}/{} failed ({}), retrying in {}ms",
+            attempt,
+            max_attempts,
+            error.kind(),
+            delay.as_millis()
+        );
+        reporter.retrying(
+            attempt + 1,
+            max_attempts,
+            delay.as_millis() as u64,
+            &request.model,
+            &error,
+        );
+
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delay_grows_within_bounds() {
+        let policy = RetryPolicy::default();
+        for _ in 0..50 {
+            let first = policy.delay(1).as_millis() as u64;
+            assert!((1000..=2000).contains(&first), "{}", first);
+            let third = policy.delay(3).as_millis() as u64;
+            assert!((4000..=8000).contains(&third), "{}", third);
+            // Capped, also where the shift would overflow
+            for attempt in [5, 40, u32::MAX] {
+                let capped = policy.delay(attempt).as_millis() as u64;
+                assert!((15_000..=30_000).contains(&capped), "{}", capped);
+            }
+        }
+    }
+
+    #[test]
+    fn falls_back_to_cheaper_models_only() {
+        assert_eq!(quota_fallback("gemini-2.5-pro"), Some("gemini-2.5-flash"));
+        assert_eq!(quota_fallback("gemini-2.5-flash"), Some("gemini-2.5-flash-lite"));
+        assert_eq!(quota_fallback("gemini-2.5-flash-lite"), None);
+        assert_eq!(quota_fallback("llama3"), None);
+    }
+}
@@ -0,0 +1,295 @@
+// src-tauri/src/selection.rs
+
+use tauri::ipc::Channel;
+use tauri::AppHandle;
+
+use crate::diff::{byte_offset, diff_texts, TextRange};
+use crate::engine::EngineRequest;
+use crate::errors::ClarezaError;
+use crate::fencing::Fence;
+use crate::gemini::{prepare_job, PromptOptions};
+use crate::jobs::{spawn_job, CompletionExtras, JobEvent};
+use crate::pipeline::RequestPipeline;
+use crate::templates::{Template, TemplateVariables, Variable};
+
+const DEFAULT_CONTEXT_PARAGRAPHS: usize = 2;
+
+/// Start of the `paragraphs`-th paragraph before the one containing `pos`.
+fn context_start(text: &str, pos: usize, paragraphs: usize) -> usize {
+    // The separator ending the previous paragraph may sit right at `pos`,
+    // or straddle it when the selection starts with a newline
+    let straddle = usize::from(text[pos..].starts_with('\n'));
+    let Some(mut start) = text[..pos + straddle].rfind("\n\n") else {
+        return 0;
+    };
+    for _ in 0..paragraphs {
+        match text[..start].trim_end().rfind("\n\n") {
+            Some(idx) => start = idx,
+            None => return 0,
+        }
+    }
+    start
+}
+
+/// End of the `paragraphs`-th paragraph after the one containing `pos`.
+fn context_end(text: &str, pos: usize, paragraphs: usize) -> usize {
+    // Same as above, for a selection ending with a newline
+    let from = pos - usize::from(text[..pos].ends_with('\n'));
+    let Some(idx) = text[from..].find("\n\n") else {
+        return text.len();
+    };
+    let mut end = from + idx;
+    for _ in 0..paragraphs {
+        let skipped = text[end..].len() - text[end..].trim_start().len();
+        match text[end + skipped..].find("\n\n") {
+            Some(idx) => end = end + skipped + idx,
+            None => return text.len(),
+        }
+    }
+    end
+}
+
+/// The selection block is left out when the template already places
+/// `{{selection}}` itself. Every block of document text is fenced, see
+/// `fencing::Fence`.
+fn build_selection_prompt(
+    template: &Template,
+    variables: &TemplateVariables,
+    before: &str,
+    selection: &str,
+    after: &str,
+) -> String {
+    let fence = Fence::for_contents(&[
+        variables.document.as_deref().unwrap_or_default(),
+        before,
+        selection,
+        after,
+    ]);
+    let mut variables = variables.clone();
+    variables.selection = Some(fence.wrap("trecho selecionado", selection));
+    variables.document = variables
+        .document
+        .map(|document| fence.wrap("documento", &document));
+
+    let instruction = template.render(&variables).replace("@file_reference", "");
+    let mut full_prompt = format!(
+        "{}{}\n\nAplique a instrução acima somente ao trecho selecionado. \
+O contexto serve apenas para manter a coerência: não o reescreva nem o inclua na resposta. \
+Responda apenas com o novo texto do trecho selecionado.",
+        fence.preamble(),
+        instruction.trim()
+    );
+
+    if !before.trim().is_empty() {
+        full_prompt.push_str(&format!(
+            "\n\nContexto anterior:\n{}",
+            fence.wrap("contexto anterior", before.trim())
+        ));
+    }
+    if !template.uses(Variable::Selection) {
+        full_prompt.push_str(&format!(
+            "\n\nTrecho selecionado:\n{}",
+            fence.wrap("trecho selecionado", selection)
+        ));
+    }
+    if !after.trim().is_empty() {
+        full_prompt.push_str(&format!(
+            "\n\nContexto posterior:\n{}",
+            fence.wrap("contexto posterior", after.trim())
+        ));
+    }
+    full_prompt
+}
+
+/// Keep the selection's own leading and trailing whitespace around the
+/// model's answer, which usually comes back trimmed.
+fn preserve_padding(selection: &str, replacement: &str) -> String {
+    let rest = selection.trim_start();
+    let leading = &selection[..selection.len() - rest.len()];
+    // From what is left, or a blank selection would be padded twice
+    let trailing = &rest[rest.trim_end().len()..];
+    format!("{}{}{}", leading, replacement.trim(), trailing)
+}
+
+/// Run a tool on a range of the document only.
+///
+/// `selection_start` and `selection_end` are UTF-16 offsets, as the editor
+/// reports them (see `TextRange`). The prompt
+/// includes `context_paragraphs` paragraphs on each side for coherence. The
+/// completion, sent as `gemini-selection-complete` rather than
+/// `gemini-complete`, carries the replacement for that range in `content` and
+/// the range itself in `selection`. Chunking options do not apply here.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn send_selection_to_gemini(
+    app_handle: AppHandle,
+    prompt: String,
+    document: String,
+    selection_start: usize,
+    selection_end: usize,
+    context_paragraphs: Option<usize>,
+    tool_id: Option<String>,
+    options: Option<PromptOptions>,
+    on_event: Option<Channel<JobEvent>>,
+) -> Result<String, ClarezaError> {
+    if selection_start >= selection_end {
+        return Err(ClarezaError::InvalidFormat("Selection is empty".to_string()));
+    }
+    let (Some(byte_start), Some(byte_end)) = (
+        byte_offset(&document, selection_start),
+        byte_offset(&document, selection_end),
+    ) else {
+        return Err(ClarezaError::InvalidFormat(
+            "Selection is outside the document".to_string(),
+        ));
+    };
+
+    let paragraphs = context_paragraphs.unwrap_or(DEFAULT_CONTEXT_PARAGRAPHS);
+    let before = &document[context_start(&document, byte_start, paragraphs)..byte_start];
+    let after = &document[byte_end..context_end(&document, byte_end, paragraphs)];
+    let selection = document[byte_start..byte_end].to_string();
+    println!(
+        "[GEMINI] Selection request: {} chars with {} + {} chars of context",
+        selection.chars().count(),
+        before.len(),
+        after.len()
+    );
+
+    let options = options.unwrap_or_default();
+    let retry = options.retry.clone().unwrap_or_default();
+    let template = Template::parse(&prompt)?;
+    let variables = TemplateVariables {
+        selection: Some(selection.clone()),
+        document: Some(document.clone()),
+        ..options.variables.clone().unwrap_or_default()
+    };
+    let mut pipeline = RequestPipeline::new(options.redaction.as_ref())?;
+    let variables = pipeline.redact_variables(&variables);
+    let prompt = build_selection_prompt(
+        &template,
+        &variables,
+        &pipeline.redact(before),
+        &pipeline.redact(&selection),
+        &pipeline.redact(after),
+    );
+    let (engine, model, reporter) = prepare_job(app_handle, tool_id, on_event).await?;
+    let request = EngineRequest {
+        prompt,
+        model,
+        timeout_secs: 120,
+        document_tools: pipeline.document_tools(),
+    };
+    let range = TextRange {
+        byte_start,
+        byte_end,
+        char_start: selection_start,
+        char_end: selection_end,
+    };
+
+    reporter.started();
+    reporter.output("❯ Processando trecho selecionado...", "system");
+    pipeline.report_masking(&reporter);
+
+    // Owned copies for the injection check inside the job
+    let (before, after) = (before.to_string(), after.to_string());
+    let job_id = spawn_job(reporter, |reporter| async move {
+        let result = pipeline
+            .generate(
+                engine.as_ref(),
+                &request,
+                &retry,
+                options.bypass_cache,
+                &[&before, &selection, &after],
+                Some(&selection),
+                &reporter,
+            )
+            .await;
+        match result {
+            Ok(checked) => {
+                let replacement = preserve_padding(&selection, &checked.content);
+                let diff = options
+                    .diff_granularity
+                    .map(|granularity| diff_texts(&selection, &replacement, granularity));
+
+                reporter.output("✅ Concluído", "system");
+                reporter.completed_as(
+                    "gemini-selection-complete",
+                    replacement,
+                    CompletionExtras {
+                        selection: Some(range),
+                        diff,
+                        injection_warning: checked.injection_warning,
+                        ..Default::default()
+                    },
+                );
+            }
+            Err(e) => {
+                println!("[GEMINI] Selection job failed: {}", e);
+                reporter.failed(&e);
+            }
+        }
+    });
+
+    Ok(job_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &str = "P1\n\nP2\n\nP3\n\nSEL\n\nA1\n\nA2\n\nA3";
+
+    fn before(text: &str, pos: usize, paragraphs: usize) -> &str {
+        text[context_start(text, pos, paragraphs)..pos].trim()
+    }
+
+    fn after(text: &str, pos: usize, paragraphs: usize) -> &str {
+        text[pos..context_end(text, pos, paragraphs)].trim()
+    }
+
+    #[test]
+    fn context_start_counts_whole_paragraphs() {
+        let pos = TEXT.find("SEL").unwrap();
+        assert_eq!(before(TEXT, pos, 0), "");
+        assert_eq!(before(TEXT, pos, 1), "P3");
+        assert_eq!(before(TEXT, pos, 2), "P2\n\nP3");
+        assert_eq!(before(TEXT, pos, 10), "P1\n\nP2\n\nP3");
+        // Selection starting with the newline of the separator
+        assert_eq!(before(TEXT, pos - 1, 1), "P3");
+    }
+
+    #[test]
+    fn context_start_within_a_paragraph() {
+        let text = "P1\n\nP2\n\nP3 SEL rest";
+        let pos = text.find("SEL").unwrap();
+        assert_eq!(before(text, pos, 0), "P3");
+        assert_eq!(before(text, pos, 1), "P2\n\nP3");
+        assert_eq!(before("no breaks SEL", 10, 1), "no breaks");
+    }
+
+    #[test]
+    fn context_end_counts_whole_paragraphs() {
+        let pos = TEXT.find("SEL").unwrap() + 3;
+        assert_eq!(after(TEXT, pos, 0), "");
+        assert_eq!(after(TEXT, pos, 1), "A1");
+        assert_eq!(after(TEXT, pos, 2), "A1\n\nA2");
+        assert_eq!(after(TEXT, pos, 10), "A1\n\nA2\n\nA3");
+        // Selection ending with the newline of the separator
+        assert_eq!(after(TEXT, pos + 1, 1), "A1");
+    }
+
+    #[test]
+    fn context_end_within_a_paragraph() {
+        let text = "SEL rest\n\nA1\n\nA2";
+        assert_eq!(after(text, 3, 0), "rest");
+        assert_eq!(after(text, 3, 1), "rest\n\nA1");
+        assert_eq!(after(text, text.len(), 1), "");
+    }
+
+    #[test]
+    fn preserve_padding_keeps_the_selection_whitespace() {
+        assert_eq!(preserve_padding("  old text \n", "new text"), "  new text \n");
+        assert_eq!(preserve_padding("old", "\n new \n"), "new");
+        assert_eq!(preserve_padding("\n\n", "new"), "\n\nnew");
+    }
+}
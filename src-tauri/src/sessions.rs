@@ -0,0 +1,467 @@
+// src-tauri/src/sessions.rs
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tauri::ipc::Channel;
+use tauri::AppHandle;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::chunking::estimate_tokens;
+use crate::engine::EngineRequest;
+use crate::errors::ClarezaError;
+use crate::gemini::{build_prompt, prepare_job, PromptOptions};
+use crate::jobs::{spawn_job, CompletionExtras, JobEvent};
+use crate::pipeline::RequestPipeline;
+use crate::templates::Template;
+use crate::utils::FileUtils;
+
+const SESSIONS_DIR: &str = "sessions";
+const DEFAULT_HISTORY_TOKENS: usize = 8000;
+
+/// Serializes read-modify-write cycles on session files.
+static SESSIONS_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    User,
+    Assistant,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionMessage {
+    pub role: Role,
+    /// For user turns, the instruction only; the document is attached to the
+    /// request that carried it but not kept in the history.
+    pub content: String,
+    pub tool_id: Option<String>,
+    pub model: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Conversation history for one document.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub id: String,
+    /// Path of the document, or any stable id the frontend uses for unsaved ones.
+    pub document_key: String,
+    /// Session this one was branched from, if any.
+    pub parent_id: Option<String>,
+    pub system_prompt: Option<String>,
+    pub messages: Vec<SessionMessage>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+async fn session_path(app_handle: &AppHandle, id: &str) -> Result<PathBuf, ClarezaError> {
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(ClarezaError::InvalidFormat(format!(
+            "Invalid session id '{}'",
+            id
+        )));
+    }
+    let dir = FileUtils::app_data_path(app_handle, SESSIONS_DIR).await?;
+    tokio::fs::create_dir_all(&dir).await?;
+    Ok(dir.join(format!("{}.json", id)))
+}
+
+async fn load_session(app_handle: &AppHandle, id: &str) -> Result<Session, ClarezaError> {
+    let path = session_path(app_handle, id).await?;
+    if !path.exists() {
+        return Err(ClarezaError::FileNotFound(format!("Session {}", id)));
+    }
+    let content = FileUtils::read_with_encoding(&path).await?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+async fn store_session(app_handle: &AppHandle, session: &Session) -> Result<(), ClarezaError> {
+    let path = session_path(app_handle, &session.id).await?;
+    FileUtils::atomic_write(&path, &serde_json::to_string_pretty(session)?).await
+}
+
+fn new_session(document_key: String, system_prompt: Option<String>) -> Session {
+    let now = Utc::now();
+    Session {
+        id: Uuid::new_v4().to_string(),
+        document_key,
+        parent_id: None,
+        system_prompt,
+        messages: Vec::new(),
+        created_at: now,
+        updated_at: now,
+    }
+}
+
+/// The most recent messages that fit in `budget` tokens, oldest first.
+/// Whole turns are dropped from the start so the history never begins with
+/// an orphan answer.
+fn trim_history(messages: &[SessionMessage], budget: usize) -> &[SessionMessage] {
+    let mut used = 0;
+    let mut start = messages.len();
+    for (idx, message) in messages.iter().enumerate().rev() {
+        used += estimate_tokens(&message.content);
+        if used > budget {
+            break;
+        }
+        start = idx;
+    }
+    while messages
+        .get(start)
+        .is_some_and(|m| m.role == Role::Assistant)
+    {
+        start += 1;
+    }
+    &messages[start..]
+}
+
+fn build_session_prompt(session: &Session, history: &[SessionMessage], request: &str) -> String {
+    let mut prompt = String::new();
+    if let Some(system) = session
+        .system_prompt
+        .as_deref()
+        .filter(|s| !s.trim().is_empty())
+    {
+        prompt.push_str(system.trim());
+        prompt.push_str("\n\n");
+    }
+
+    if !history.is_empty() {
+        prompt.push_str("Histórico da conversa até agora:\n");
+        for message in history {
+            let speaker = match message.role {
+                Role::User => "Usuário",
+                Role::Assistant => "Assistente",
+            };
+            prompt.push_str(&format!("\n[{}]\n{}\n", speaker, message.content.trim()));
+        }
+        prompt.push_str(
+            "\nResponda à nova solicitação abaixo levando em conta o histórico. \
+Se ela se referir ao texto anterior, use a última resposta do assistente.\n\n",
+        );
+    }
+
+    prompt.push_str(request);
+    prompt
+}
+
+#[tauri::command]
+pub async fn create_session(
+    app_handle: AppHandle,
+    document_key: String,
+    system_prompt: Option<String>,
+) -> Result<Session, ClarezaError> {
+    let session = new_session(document_key, system_prompt);
+    let _guard = SESSIONS_LOCK.lock().await;
+    store_session(&app_handle, &session).await?;
+
+    println!(
+        "[SESSIONS] Created session {} for {}",
+        session.id, session.document_key
+    );
+    Ok(session)
+}
+
+#[tauri::command]
+pub async fn get_session(
+    app_handle: AppHandle,
+    session_id: String,
+) -> Result<Session, ClarezaError> {
+    let _guard = SESSIONS_LOCK.lock().await;
+    load_session(&app_handle, &session_id).await
+}
+
+/// Sessions of a document, most recently used first.
+#[tauri::command]
+pub async fn list_sessions(
+    app_handle: AppHandle,
+    document_key: String,
+) -> Result<Vec<Session>, ClarezaError> {
+    let _guard = SESSIONS_LOCK.lock().await;
+    let dir = FileUtils::app_data_path(&app_handle, SESSIONS_DIR).await?;
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut sessions = Vec::new();
+    let mut entries = tokio::fs::read_dir(&dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+            continue;
+        }
+        let content = FileUtils::read_with_encoding(&path).await?;
+        match serde_json::from_str::<Session>(&content) {
+            Ok(session) if session.document_key == document_key => sessions.push(session),
+            Ok(_) => {}
+            Err(e) => println!("[SESSIONS] Skipping unreadable {}: {}", path.display(), e),
+        }
+    }
+
+    sessions.sort_by_key(|s| std::cmp::Reverse(s.updated_at));
+    Ok(sessions)
+}
+
+/// Start a new session from the first `message_count` messages of another
+/// (all of them by default), leaving the original untouched.
+#[tauri::command]
+pub async fn branch_session(
+    app_handle: AppHandle,
+    session_id: String,
+    message_count: Option<usize>,
+) -> Result<Session, ClarezaError> {
+    let _guard = SESSIONS_LOCK.lock().await;
+    let source = load_session(&app_handle, &session_id).await?;
+    let keep = message_count.unwrap_or(source.messages.len());
+    if keep > source.messages.len() {
+        return Err(ClarezaError::InvalidFormat(format!(
+            "Session has only {} messages",
+            source.messages.len()
+        )));
+    }
+
+    let mut branch = new_session(source.document_key, source.system_prompt);
+    branch.parent_id = Some(source.id);
+    branch.messages = source.messages[..keep].to_vec();
+    store_session(&app_handle, &branch).await?;
+
+    println!(
+        "[SESSIONS] Branched {} into {} at {}",
+        session_id, branch.id, keep
+    );
+    Ok(branch)
+}
+
+/// Drop the history but keep the session and its system prompt.
+#[tauri::command]
+pub async fn clear_session(
+    app_handle: AppHandle,
+    session_id: String,
+) -> Result<Session, ClarezaError> {
+    let _guard = SESSIONS_LOCK.lock().await;
+    let mut session = load_session(&app_handle, &session_id).await?;
+    session.messages.clear();
+    session.updated_at = Utc::now();
+    store_session(&app_handle, &session).await?;
+    Ok(session)
+}
+
+#[tauri::command]
+pub async fn delete_session(app_handle: AppHandle, session_id: String) -> Result<(), ClarezaError> {
+    let _guard = SESSIONS_LOCK.lock().await;
+    let path = session_path(&app_handle, &session_id).await?;
+    if !path.exists() {
+        return Err(ClarezaError::FileNotFound(format!(
+            "Session {}",
+            session_id
+        )));
+    }
+    tokio::fs::remove_file(path).await?;
+    Ok(())
+}
+
+/// Send a follow-up prompt within a session.
+///
+/// The request carries the system prompt and as much recent history as fits
+/// in `history_tokens` (estimated), followed by the new prompt with
+/// `file_content` attached as in `send_prompt_to_gemini`. Documents are not
+/// chunked here. When the job completes, both turns are appended to the
+/// session. The answer is sent as `gemini-session-complete`: a reply in a
+/// conversation is not necessarily a new version of the document.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn send_session_prompt(
+    app_handle: AppHandle,
+    session_id: String,
+    prompt: String,
+    file_content: Option<String>,
+    tool_id: Option<String>,
+    history_tokens: Option<usize>,
+    options: Option<PromptOptions>,
+    on_event: Option<Channel<JobEvent>>,
+) -> Result<String, ClarezaError> {
+    let session = {
+        let _guard = SESSIONS_LOCK.lock().await;
+        load_session(&app_handle, &session_id).await?
+    };
+
+    let options = options.unwrap_or_default();
+    let retry = options.retry.clone().unwrap_or_default();
+    let variables = options.variables.clone().unwrap_or_default();
+    let template = Template::parse(&prompt)?;
+
+    let history = trim_history(
+        &session.messages,
+        history_tokens.unwrap_or(DEFAULT_HISTORY_TOKENS),
+    );
+    println!(
+        "[SESSIONS] Session {}: sending {} of {} messages",
+        session.id,
+        history.len(),
+        session.messages.len()
+    );
+    let full_prompt = build_session_prompt(
+        &session,
+        history,
+        &build_prompt(&template, &variables, file_content.as_deref()),
+    );
+    // The history is stored unmasked, so the whole request is masked at once
+    let mut pipeline = RequestPipeline::new(options.redaction.as_ref())?;
+    let full_prompt = pipeline.redact(&full_prompt);
+
+    let (engine, model, reporter) =
+        prepare_job(app_handle.clone(), tool_id.clone(), on_event).await?;
+    let request = EngineRequest {
+        prompt: full_prompt,
+        model,
+        timeout_secs: 120,
+        document_tools: pipeline.document_tools(),
+    };
+    let instruction = template.render(&variables).replace("@file_reference", "");
+
+    reporter.started();
+    reporter.output(format!("❯ {}", instruction.trim()), "system");
+    pipeline.report_masking(&reporter);
+
+    let job_id = spawn_job(reporter, |reporter| async move {
+        let sources = [
+            file_content.as_deref().unwrap_or_default(),
+            variables.selection.as_deref().unwrap_or_default(),
+        ];
+        let result = pipeline
+            .generate(
+                engine.as_ref(),
+                &request,
+                &retry,
+                options.bypass_cache,
+                &sources,
+                None,
+                &reporter,
+            )
+            .await;
+        match result {
+            Ok(checked) => {
+                let now = Utc::now();
+                let turns = [
+                    SessionMessage {
+                        role: Role::User,
+                        content: instruction.trim().to_string(),
+                        tool_id: tool_id.clone(),
+                        model: None,
+                        created_at: now,
+                    },
+                    SessionMessage {
+                        role: Role::Assistant,
+                        content: checked.content.clone(),
+                        tool_id,
+                        model: Some(request.model.clone()),
+                        created_at: now,
+                    },
+                ];
+
+                // Reload so turns recorded meanwhile by another job are kept
+                let _guard = SESSIONS_LOCK.lock().await;
+                let saved = match load_session(&app_handle, &session_id).await {
+                    Ok(mut session) => {
+                        session.messages.extend(turns);
+                        session.updated_at = now;
+                        store_session(&app_handle, &session).await
+                    }
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = saved {
+                    println!("[SESSIONS] Could not record turn in {}: {}", session_id, e);
+                }
+
+                reporter.output("✅ Concluído", "system");
+                reporter.completed_as(
+                    "gemini-session-complete",
+                    checked.content,
+                    CompletionExtras {
+                        injection_warning: checked.injection_warning,
+                        ..Default::default()
+                    },
+                );
+            }
+            Err(e) => {
+                println!("[SESSIONS] Session job failed: {}", e);
+                reporter.failed(&e);
+            }
+        }
+    });
+
+    Ok(job_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(role: Role, content: &str) -> SessionMessage {
+        SessionMessage {
+            role,
+            content: content.to_string(),
+            tool_id: None,
+            model: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    fn contents(messages: &[SessionMessage]) -> Vec<&str> {
+        messages.iter().map(|m| m.content.as_str()).collect()
+    }
+
+    #[test]
+    fn trim_history_keeps_the_latest_turns_in_order() {
+        // Four characters per token
+        let messages = vec![
+            message(Role::User, "aaaa"),
+            message(Role::Assistant, "bbbb"),
+            message(Role::User, "cccc"),
+            message(Role::Assistant, "dddd"),
+        ];
+        assert_eq!(contents(trim_history(&messages, 100)), ["aaaa", "bbbb", "cccc", "dddd"]);
+        assert_eq!(contents(trim_history(&messages, 2)), ["cccc", "dddd"]);
+        assert!(trim_history(&messages, 0).is_empty());
+    }
+
+    #[test]
+    fn trim_history_never_starts_with_an_answer() {
+        let messages = vec![
+            message(Role::User, "aaaa"),
+            message(Role::Assistant, "bbbb"),
+            message(Role::User, "cccc"),
+            message(Role::Assistant, "dddd"),
+        ];
+        assert_eq!(contents(trim_history(&messages, 3)), ["cccc", "dddd"]);
+        assert!(trim_history(&messages, 1).is_empty());
+    }
+
+    #[test]
+    fn session_prompt_keeps_the_system_prompt() {
+        let mut session = new_session("doc".to_string(), Some("  Seja breve.  ".to_string()));
+        session.messages = vec![
+            message(Role::User, "Resuma"),
+            message(Role::Assistant, "Resumo"),
+        ];
+
+        let prompt = build_session_prompt(&session, &session.messages, "Agora traduza");
+        assert!(prompt.starts_with("Seja breve.\n\n"));
+        let user = prompt.find("[Usuário]\nResuma").unwrap();
+        let assistant = prompt.find("[Assistente]\nResumo").unwrap();
+        assert!(user < assistant);
+        assert!(prompt.ends_with("Agora traduza"));
+
+        // Even with the whole history trimmed away
+        let prompt = build_session_prompt(&session, &[], "Agora traduza");
+        assert_eq!(prompt, "Seja breve.\n\nAgora traduza");
+    }
+
+    #[test]
+    fn session_prompt_without_system_prompt_or_history() {
+        let session = new_session("doc".to_string(), Some("   ".to_string()));
+        assert_eq!(build_session_prompt(&session, &[], "Revise"), "Revise");
+    }
+}
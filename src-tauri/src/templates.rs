@@ -0,0 +1,342 @@
+// src-tauri/src/templates.rs
+
+use serde::{Deserialize, Serialize};
+
+use crate::errors::ClarezaError;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GlossaryEntry {
+    pub term: String,
+    pub definition: String,
+}
+
+/// Values available to prompt templates. Unset values render as empty text
+/// and are false in conditionals.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TemplateVariables {
+    pub selection: Option<String>,
+    pub document: Option<String>,
+    pub title: Option<String>,
+    pub language: Option<String>,
+    pub tone: Option<String>,
+    pub tags: Vec<String>,
+    pub glossary: Vec<GlossaryEntry>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variable {
+    Selection,
+    Document,
+    Title,
+    Language,
+    Tone,
+    Tags,
+    Glossary,
+}
+
+impl Variable {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "selection" => Some(Variable::Selection),
+            "document" => Some(Variable::Document),
+            "title" => Some(Variable::Title),
+            "language" => Some(Variable::Language),
+            "tone" => Some(Variable::Tone),
+            "tags" => Some(Variable::Tags),
+            "glossary" => Some(Variable::Glossary),
+            _ => None,
+        }
+    }
+}
+
+impl TemplateVariables {
+    fn value(&self, variable: Variable) -> String {
+        let text = |value: &Option<String>| value.clone().unwrap_or_default();
+        match variable {
+            Variable::Selection => text(&self.selection),
+            Variable::Document => text(&self.document),
+            Variable::Title => text(&self.title),
+            Variable::Language => text(&self.language),
+            Variable::Tone => text(&self.tone),
+            Variable::Tags => self.tags.join(", "),
+            Variable::Glossary => self
+                .glossary
+                .iter()
+                .map(|entry| format!("- {}: {}", entry.term, entry.definition))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+
+    fn is_set(&self, variable: Variable) -> bool {
+        let filled =
+            |value: &Option<String>| value.as_deref().is_some_and(|v| !v.trim().is_empty());
+        match variable {
+            Variable::Selection => filled(&self.selection),
+            Variable::Document => filled(&self.document),
+            Variable::Title => filled(&self.title),
+            Variable::Language => filled(&self.language),
+            Variable::Tone => filled(&self.tone),
+            Variable::Tags => !self.tags.is_empty(),
+            Variable::Glossary => !self.glossary.is_empty(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Text(String),
+    Value(Variable),
+    Condition {
+        variable: Variable,
+        negated: bool,
+        then: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+}
+
+/// An open `{{#if}}` or `{{#unless}}` block while parsing.
+struct Block {
+    keyword: &'static str,
+    variable: Variable,
+    then: Vec<Node>,
+    otherwise: Option<Vec<Node>>,
+}
+
+/// A parsed prompt template.
+///
+/// Syntax: `{{name}}` inserts a variable, `{{#if name}}…{{else}}…{{/if}}`
+/// and `{{#unless name}}…{{/unless}}` test whether it is set, and `\{{`
+/// produces a literal `{{`. Inserted values are never parsed again, so a
+/// document containing braces cannot change the template.
+#[derive(Debug, Clone)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+fn template_error(message: String, position: usize) -> ClarezaError {
+    ClarezaError::InvalidFormat(format!(
+        "Template error at position {}: {}",
+        position, message
+    ))
+}
+
+fn parse_variable(name: &str, position: usize) -> Result<Variable, ClarezaError> {
+    Variable::from_name(name.trim())
+        .ok_or_else(|| template_error(format!("unknown variable '{}'", name.trim()), position))
+}
+
+impl Template {
+    pub fn parse(source: &str) -> Result<Self, ClarezaError> {
+        let mut root: Vec<Node> = Vec::new();
+        let mut blocks: Vec<Block> = Vec::new();
+        let mut text = String::new();
+        let mut rest = source;
+
+        fn current<'a>(root: &'a mut Vec<Node>, blocks: &'a mut [Block]) -> &'a mut Vec<Node> {
+            match blocks.last_mut() {
+                Some(block) => block.otherwise.as_mut().unwrap_or(&mut block.then),
+                None => root,
+            }
+        }
+
+        while let Some(open) = rest.find("{{") {
+            if rest[..open].ends_with('\\') {
+                text.push_str(&rest[..open - 1]);
+                text.push_str("{{");
+                rest = &rest[open + 2..];
+                continue;
+            }
+
+            let position = source.len() - rest.len() + open;
+            text.push_str(&rest[..open]);
+            let after = &rest[open + 2..];
+            let close = after
+                .find("}}")
+                .ok_or_else(|| template_error("unclosed '{{'".to_string(), position))?;
+            let tag = after[..close].trim();
+            rest = &after[close + 2..];
+
+            if !text.is_empty() {
+                current(&mut root, &mut blocks).push(Node::Text(std::mem::take(&mut text)));
+            }
+
+            if let Some(name) = tag.strip_prefix("#if ") {
+                blocks.push(Block {
+                    keyword: "if",
+                    variable: parse_variable(name, position)?,
+                    then: Vec::new(),
+                    otherwise: None,
+                });
+            } else if let Some(name) = tag.strip_prefix("#unless ") {
+                blocks.push(Block {
+                    keyword: "unless",
+                    variable: parse_variable(name, position)?,
+                    then: Vec::new(),
+                    otherwise: None,
+                });
+            } else if tag == "else" {
+                match blocks.last_mut() {
+                    Some(block) if block.otherwise.is_none() => block.otherwise = Some(Vec::new()),
+                    Some(_) => {
+                        return Err(template_error("duplicate {{else}}".to_string(), position))
+                    }
+                    None => {
+                        return Err(template_error(
+                            "{{else}} outside a block".to_string(),
+                            position,
+                        ))
+                    }
+                }
+            } else if let Some(keyword) = tag.strip_prefix('/') {
+                let block = blocks
+                    .pop()
+                    .filter(|block| block.keyword == keyword.trim())
+                    .ok_or_else(|| {
+                        template_error(format!("unexpected {{{{/{}}}}}", keyword.trim()), position)
+                    })?;
+                let node = Node::Condition {
+                    variable: block.variable,
+                    negated: block.keyword == "unless",
+                    then: block.then,
+                    otherwise: block.otherwise.unwrap_or_default(),
+                };
+                current(&mut root, &mut blocks).push(node);
+            } else {
+                let variable = parse_variable(tag, position)?;
+                current(&mut root, &mut blocks).push(Node::Value(variable));
+            }
+        }
+
+        if let Some(block) = blocks.last() {
+            return Err(template_error(
+                format!("missing {{{{/{}}}}}", block.keyword),
+                source.len(),
+            ));
+        }
+        text.push_str(rest);
+        if !text.is_empty() {
+            root.push(Node::Text(text));
+        }
+
+        Ok(Self { nodes: root })
+    }
+
+    pub fn render(&self, variables: &TemplateVariables) -> String {
+        fn render_nodes(nodes: &[Node], variables: &TemplateVariables, out: &mut String) {
+            for node in nodes {
+                match node {
+                    Node::Text(text) => out.push_str(text),
+                    Node::Value(variable) => out.push_str(&variables.value(*variable)),
+                    Node::Condition {
+                        variable,
+                        negated,
+                        then,
+                        otherwise,
+                    } => {
+                        let branch = if variables.is_set(*variable) != *negated {
+                            then
+                        } else {
+                            otherwise
+                        };
+                        render_nodes(branch, variables, out);
+                    }
+                }
+            }
+        }
+
+        let mut out = String::new();
+        render_nodes(&self.nodes, variables, &mut out);
+        out
+    }
+
+    /// Whether the template inserts `variable` anywhere, in any branch.
+    pub fn uses(&self, variable: Variable) -> bool {
+        fn search(nodes: &[Node], variable: Variable) -> bool {
+            nodes.iter().any(|node| match node {
+                Node::Text(_) => false,
+                Node::Value(v) => *v == variable,
+                Node::Condition {
+                    then, otherwise, ..
+                } => search(then, variable) || search(otherwise, variable),
+            })
+        }
+        search(&self.nodes, variable)
+    }
+}
+
+/// Render a template without running it, for previews in the tool editor.
+#[tauri::command]
+pub fn render_prompt_template(
+    template: String,
+    variables: Option<TemplateVariables>,
+) -> Result<String, ClarezaError> {
+    Ok(Template::parse(&template)?.render(&variables.unwrap_or_default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(source: &str, variables: &TemplateVariables) -> String {
+        Template::parse(source).unwrap().render(variables)
+    }
+
+    #[test]
+    fn renders_values_and_conditions() {
+        let variables = TemplateVariables {
+            title: Some("Relatório".to_string()),
+            tags: vec!["a".to_string(), "b".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(render("Título: {{ title }}", &variables), "Título: Relatório");
+        assert_eq!(render("{{tags}}", &variables), "a, b");
+        assert_eq!(
+            render("{{#if tone}}tom {{tone}}{{else}}tom neutro{{/if}}", &variables),
+            "tom neutro"
+        );
+        assert_eq!(render("{{#unless selection}}tudo{{/unless}}", &variables), "tudo");
+        assert_eq!(render("{{#if title}}{{#if tags}}ambos{{/if}}{{/if}}", &variables), "ambos");
+    }
+
+    #[test]
+    fn escaped_braces_are_literal() {
+        let variables = TemplateVariables::default();
+        assert_eq!(render(r#"JSON: \{{ "a": 1 }}"#, &variables), r#"JSON: {{ "a": 1 }}"#);
+        assert_eq!(render(r"\{{title}}", &variables), "{{title}}");
+    }
+
+    #[test]
+    fn inserted_values_are_not_parsed() {
+        let variables = TemplateVariables {
+            document: Some("{{#if title}}{{title}}".to_string()),
+            title: Some("secreto".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(render("{{document}}", &variables), "{{#if title}}{{title}}");
+    }
+
+    #[test]
+    fn rejects_malformed_templates() {
+        for source in [
+            "{{title",
+            "{{unknown}}",
+            "{{#if title}}sem fim",
+            "{{/if}}",
+            "{{else}}",
+            "{{#if title}}{{else}}{{else}}{{/if}}",
+            "{{#if title}}{{/unless}}",
+        ] {
+            assert!(Template::parse(source).is_err(), "{}", source);
+        }
+    }
+
+    #[test]
+    fn finds_variables_in_every_branch() {
+        let template = Template::parse("{{#if tone}}{{else}}{{selection}}{{/if}}").unwrap();
+        assert!(template.uses(Variable::Selection));
+        assert!(!template.uses(Variable::Tone));
+        assert!(!template.uses(Variable::Document));
+    }
+}
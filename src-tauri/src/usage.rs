@@ -0,0 +1,208 @@
+// src-tauri/src/usage.rs
+
+use chrono::{DateTime, Local, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tauri::AppHandle;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+use crate::chunking::estimate_tokens;
+use crate::errors::{AiError, ClarezaError};
+use crate::jobs::JobReporter;
+use crate::utils::FileUtils;
+
+const LEDGER_FILE: &str = "usage.jsonl";
+
+/// Keeps appended lines from interleaving.
+static LEDGER_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UsageOutcome {
+    Success,
+    /// Answered from the response cache, no quota spent.
+    Cached,
+    Error,
+}
+
+/// One request sent to (or answered on behalf of) a provider.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageRecord {
+    pub timestamp: DateTime<Utc>,
+    pub job_id: String,
+    pub tool_id: Option<String>,
+    pub provider: String,
+    pub model: String,
+    pub duration_ms: u64,
+    pub prompt_bytes: usize,
+    pub response_bytes: usize,
+    /// Estimates, see `chunking::estimate_tokens`.
+    pub prompt_tokens: usize,
+    pub response_tokens: usize,
+    pub outcome: UsageOutcome,
+    pub error_kind: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UsageGrouping {
+    Day,
+    Tool,
+    Model,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UsageAggregate {
+    pub key: String,
+    pub requests: u64,
+    pub successes: u64,
+    pub failures: u64,
+    pub cached: u64,
+    pub prompt_bytes: u64,
+    pub response_bytes: u64,
+    pub prompt_tokens: u64,
+    pub response_tokens: u64,
+    pub total_duration_ms: u64,
+}
+
+/// Append a record for one engine call to the ledger. Failures to write are
+/// logged only, bookkeeping must never break a request.
+pub async fn record_usage(
+    reporter: &JobReporter,
+    provider: &str,
+    model: &str,
+    prompt: &str,
+    result: Result<&str, &AiError>,
+    cached: bool,
+    duration_ms: u64,
+) {
+    let response = result.unwrap_or_default();
+    let record = UsageRecord {
+        timestamp: Utc::now(),
+        job_id: reporter.job_id().to_string(),
+        tool_id: reporter.tool_id().map(str::to_string),
+        provider: provider.to_string(),
+        model: model.to_string(),
+        duration_ms,
+        prompt_bytes: prompt.len(),
+        response_bytes: response.len(),
+        prompt_tokens: estimate_tokens(prompt),
+        response_tokens: estimate_tokens(response),
+        outcome: match (&result, cached) {
+            (Err(_), _) => UsageOutcome::Error,
+            (Ok(_), true) => UsageOutcome::Cached,
+            (Ok(_), false) => UsageOutcome::Success,
+        },
+        error_kind: result.err().map(|e| e.kind().to_string()),
+    };
+
+    if let Err(e) = append_record(reporter.app_handle(), &record).await {
+        println!("[USAGE] Could not record usage: {}", e);
+    }
+}
+
+async fn append_record(app_handle: &AppHandle, record: &UsageRecord) -> Result<(), ClarezaError> {
+    let path = FileUtils::app_data_path(app_handle, LEDGER_FILE).await?;
+    let mut line = serde_json::to_string(record)?;
+    line.push('\n');
+
+    let _guard = LEDGER_LOCK.lock().await;
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .await?;
+    file.write_all(line.as_bytes()).await?;
+    Ok(())
+}
+
+async fn read_records(app_handle: &AppHandle) -> Result<Vec<UsageRecord>, ClarezaError> {
+    let path = FileUtils::app_data_path(app_handle, LEDGER_FILE).await?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let _guard = LEDGER_LOCK.lock().await;
+    let content = FileUtils::read_with_encoding(&path).await?;
+    // A crash mid-write can leave a partial last line, skip what does not parse
+    Ok(content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// Usage totals grouped by local day, tool or model, optionally limited to
+/// records between `since` and `until`. Sorted by key.
+#[tauri::command]
+pub async fn get_usage_summary(
+    app_handle: AppHandle,
+    group_by: UsageGrouping,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+) -> Result<Vec<UsageAggregate>, ClarezaError> {
+    let mut groups: BTreeMap<String, UsageAggregate> = BTreeMap::new();
+
+    for record in read_records(&app_handle).await? {
+        if since.is_some_and(|since| record.timestamp < since)
+            || until.is_some_and(|until| record.timestamp >= until)
+        {
+            continue;
+        }
+
+        let key = match group_by {
+            UsageGrouping::Day => record
+                .timestamp
+                .with_timezone(&Local)
+                .format("%Y-%m-%d")
+                .to_string(),
+            UsageGrouping::Tool => record.tool_id.clone().unwrap_or_else(|| "-".to_string()),
+            UsageGrouping::Model => format!("{} / {}", record.provider, record.model),
+        };
+        let group = groups.entry(key.clone()).or_insert_with(|| UsageAggregate {
+            key,
+            ..Default::default()
+        });
+
+        group.requests += 1;
+        match record.outcome {
+            UsageOutcome::Success => group.successes += 1,
+            UsageOutcome::Cached => group.cached += 1,
+            UsageOutcome::Error => group.failures += 1,
+        }
+        group.prompt_bytes += record.prompt_bytes as u64;
+        group.response_bytes += record.response_bytes as u64;
+        group.prompt_tokens += record.prompt_tokens as u64;
+        group.response_tokens += record.response_tokens as u64;
+        group.total_duration_ms += record.duration_ms;
+    }
+
+    Ok(groups.into_values().collect())
+}
+
+/// The most recent `limit` records, newest first.
+#[tauri::command]
+pub async fn get_usage_records(
+    app_handle: AppHandle,
+    limit: Option<usize>,
+) -> Result<Vec<UsageRecord>, ClarezaError> {
+    let records = read_records(&app_handle).await?;
+    Ok(records
+        .into_iter()
+        .rev()
+        .take(limit.unwrap_or(100))
+        .collect())
+}
+
+#[tauri::command]
+pub async fn clear_usage_ledger(app_handle: AppHandle) -> Result<(), ClarezaError> {
+    let path = FileUtils::app_data_path(&app_handle, LEDGER_FILE).await?;
+    let _guard = LEDGER_LOCK.lock().await;
+    if path.exists() {
+        tokio::fs::remove_file(&path).await?;
+    }
+    println!("[USAGE] Ledger cleared");
+    Ok(())
+}
@@ -1,8 +1,10 @@
 // src-tauri/src/utils.rs
+use crate::encoding;
 use crate::errors::ClarezaError;
 // use crate::models::{ClarezaDocument, DocumentMetadata, BackupInfo};
 use crate::models::DocumentMetadata;
 use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
 // use std::fs;
 use chrono::Utc;
 use uuid::Uuid;
@@ -17,6 +19,17 @@ impl FileUtils {
             .map_err(|e| ClarezaError::Path(format!("Cannot canonicalize path: {}", e)))
     }
 
+    /// Canonical directory plus file name, which also works for a file that
+    /// does not exist (yet, or anymore). Used to key per-document state.
+    pub fn document_key(path: &Path) -> PathBuf {
+        match (path.parent(), path.file_name()) {
+            (Some(parent), Some(name)) => Self::safe_canonicalize(parent)
+                .map(|parent| parent.join(name))
+                .unwrap_or_else(|_| path.to_path_buf()),
+            _ => path.to_path_buf(),
+        }
+    }
+
     /// Create a backup of a file with timestamp
     pub fn create_backup_path(original_path: &Path) -> Result<PathBuf, ClarezaError> {
         let parent = original_path
@@ -45,11 +58,31 @@ impl FileUtils {
 
     /// Atomically write content to a file
     pub async fn atomic_write<P: AsRef<Path>>(path: P, content: &str) -> Result<(), ClarezaError> {
+        Self::atomic_write_bytes(path, content.as_bytes()).await
+    }
+
+    /// Like `atomic_write`, for content already encoded for disk
+    pub async fn atomic_write_bytes<P: AsRef<Path>>(path: P, bytes: &[u8]) -> Result<(), ClarezaError> {
         let path = path.as_ref();
         let temp_path = path.with_extension("tmp");
 
         // Write to temporary file first
-        tokio::fs::write(&temp_path, content.as_bytes()).await?;
+        tokio::fs::write(&temp_path, bytes).await?;
+
+        // The rename replaces the file, keep the mode and owner it had
+        if let Ok(original) = tokio::fs::metadata(path).await {
+            tokio::fs::set_permissions(&temp_path, original.permissions()).await?;
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::MetadataExt;
+                // Only possible for the owner's group or as root, best effort
+                let _ = std::os::unix::fs::chown(
+                    &temp_path,
+                    Some(original.uid()),
+                    Some(original.gid()),
+                );
+            }
+        }
 
         // Atomically move temp file to target
         tokio::fs::rename(temp_path, path).await?;
@@ -66,10 +99,20 @@ impl FileUtils {
             .ok_or_else(|| ClarezaError::Path("Invalid file name".to_string()))?;
         Ok(parent.join(".clareza_versions").join(file_name))
     }
+    /// Read a text file in whatever encoding it was saved, see `encoding::decode`
     pub async fn read_with_encoding<P: AsRef<Path>>(path: P) -> Result<String, ClarezaError> {
         let bytes = tokio::fs::read(path).await?;
-        String::from_utf8(bytes)
-            .map_err(|_| ClarezaError::InvalidFormat("File is not valid UTF-8".to_string()))
+        Ok(encoding::decode(&bytes).content)
+    }
+
+    /// Path of `name` inside the app data directory, creating the directory
+    pub async fn app_data_path(app_handle: &AppHandle, name: &str) -> Result<PathBuf, ClarezaError> {
+        let dir = app_handle
+            .path()
+            .app_data_dir()
+            .map_err(|e| ClarezaError::Path(format!("Cannot determine app data directory: {}", e)))?;
+        tokio::fs::create_dir_all(&dir).await?;
+        Ok(dir.join(name))
     }
 
     // /// Get application data directory
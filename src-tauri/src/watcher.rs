@@ -0,0 +1,375 @@
+// src-tauri/src/watcher.rs
+
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::{mpsc, Mutex};
+use tokio::time::timeout;
+
+use crate::diff::{merge_texts, MergeResult};
+use crate::encoding;
+use crate::errors::ClarezaError;
+use crate::utils::FileUtils;
+
+/// Editors and sync tools write in bursts, changes are checked once quiet.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+const OUR_LABEL: &str = "Clareza";
+const THEIR_LABEL: &str = "Disco";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExternalChangeKind {
+    Modified,
+    Deleted,
+    Renamed,
+}
+
+/// Payload of the `document-changed-externally` event.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExternalChange {
+    pub path: String,
+    pub kind: ExternalChangeKind,
+    pub new_path: Option<String>,
+}
+
+/// What `save_document` does when the file changed on disk since Clareza
+/// last read or wrote it.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictStrategy {
+    #[default]
+    Refuse,
+    Overwrite,
+    /// Three-way merge with the disk version; refused if any region conflicts.
+    Merge,
+}
+
+struct TrackedDocument {
+    /// Hash of the file as Clareza last read or wrote it.
+    hash: String,
+    /// That same content, the base of a three-way merge.
+    base: String,
+    /// Last external state reported, so each change is reported once.
+    reported: Option<String>,
+}
+
+struct WatchState {
+    watcher: RecommendedWatcher,
+    documents: HashMap<PathBuf, TrackedDocument>,
+    /// Watched directories and how many tracked documents each holds.
+    dirs: HashMap<PathBuf, usize>,
+}
+
+static WATCH_STATE: Lazy<Mutex<Option<WatchState>>> = Lazy::new(|| Mutex::new(None));
+
+fn content_hash(content: &[u8]) -> String {
+    Sha256::digest(content)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+impl WatchState {
+    /// Files are usually replaced rather than rewritten (our own saves
+    /// included), which drops a watch on the file itself, so the parent
+    /// directory is watched instead.
+    fn watch_dir(&mut self, key: &Path) {
+        let Some(dir) = key.parent() else {
+            return;
+        };
+        let count = self.dirs.entry(dir.to_path_buf()).or_insert(0);
+        if *count == 0 {
+            if let Err(e) = self.watcher.watch(dir, RecursiveMode::NonRecursive) {
+                println!("[WATCHER] Cannot watch {}: {}", dir.display(), e);
+            }
+        }
+        *count += 1;
+    }
+
+    fn unwatch_dir(&mut self, key: &Path) {
+        let Some(dir) = key.parent() else {
+            return;
+        };
+        let Some(count) = self.dirs.get_mut(dir) else {
+            return;
+        };
+        *count -= 1;
+        if *count == 0 {
+            self.dirs.remove(dir);
+            let _ = self.watcher.unwatch(dir);
+        }
+    }
+
+    /// Remember `content`, stored as `bytes`, as the version Clareza knows.
+    /// Returns what was tracked before.
+    fn track(&mut self, key: &Path, bytes: &[u8], content: &str) -> Option<TrackedDocument> {
+        let document = TrackedDocument {
+            hash: content_hash(bytes),
+            base: content.to_string(),
+            reported: None,
+        };
+        let previous = self.documents.insert(key.to_path_buf(), document);
+        if previous.is_none() {
+            self.watch_dir(key);
+        }
+        previous
+    }
+}
+
+fn emit_change(app_handle: &AppHandle, change: ExternalChange) {
+    println!("[WATCHER] {:?}: {}", change.kind, change.path);
+    if let Err(e) = app_handle.emit("document-changed-externally", change) {
+        println!("[WATCHER] Failed to emit change event: {:?}", e);
+    }
+}
+
+/// Compare a tracked file with what Clareza last saw and report the change.
+async fn check_path(app_handle: &AppHandle, key: &Path) {
+    let disk = tokio::fs::read(key).await.ok();
+    let mut state = WATCH_STATE.lock().await;
+    let Some(document) = state.as_mut().and_then(|s| s.documents.get_mut(key)) else {
+        return;
+    };
+
+    let (current, kind) = match &disk {
+        None => ("deleted".to_string(), ExternalChangeKind::Deleted),
+        Some(bytes) => (content_hash(bytes), ExternalChangeKind::Modified),
+    };
+    if current == document.hash {
+        document.reported = None;
+        return;
+    }
+    if document.reported.as_deref() == Some(current.as_str()) {
+        return;
+    }
+    document.reported = Some(current);
+    emit_change(
+        app_handle,
+        ExternalChange {
+            path: key.to_string_lossy().to_string(),
+            kind,
+            new_path: None,
+        },
+    );
+}
+
+/// Follow a tracked document to its new name.
+async fn handle_rename(app_handle: &AppHandle, from: &Path, to: &Path) {
+    let (from, to) = (FileUtils::document_key(from), FileUtils::document_key(to));
+    let mut guard = WATCH_STATE.lock().await;
+    let Some(state) = guard.as_mut() else {
+        return;
+    };
+    let Some(document) = state.documents.remove(&from) else {
+        return;
+    };
+    state.unwatch_dir(&from);
+    state.watch_dir(&to);
+    state.documents.insert(to.clone(), document);
+    emit_change(
+        app_handle,
+        ExternalChange {
+            path: from.to_string_lossy().to_string(),
+            kind: ExternalChangeKind::Renamed,
+            new_path: Some(to.to_string_lossy().to_string()),
+        },
+    );
+}
+
+fn start_watcher(app_handle: AppHandle) -> Result<RecommendedWatcher, ClarezaError> {
+    let (tx, mut rx) = mpsc::unbounded_channel::<Event>();
+    let watcher = notify::recommended_watcher(move |result: notify::Result<Event>| match result {
+        Ok(event) => {
+            let _ = tx.send(event);
+        }
+        Err(e) => println!("[WATCHER] Watch error: {}", e),
+    })
+    .map_err(|e| ClarezaError::Path(format!("Cannot start file watcher: {}", e)))?;
+
+    tokio::spawn(async move {
+        let mut pending: HashSet<PathBuf> = HashSet::new();
+        loop {
+            let event = if pending.is_empty() {
+                rx.recv().await
+            } else {
+                match timeout(DEBOUNCE, rx.recv()).await {
+                    Ok(event) => event,
+                    Err(_) => {
+                        for key in pending.drain() {
+                            check_path(&app_handle, &key).await;
+                        }
+                        continue;
+                    }
+                }
+            };
+            let Some(event) = event else {
+                break;
+            };
+
+            match (&event.kind, event.paths.as_slice()) {
+                (EventKind::Modify(ModifyKind::Name(RenameMode::Both)), [from, to]) => {
+                    handle_rename(&app_handle, from, to).await;
+                    // Replacing a file by renaming a temp file over it
+                    pending.insert(FileUtils::document_key(to));
+                }
+                _ => pending.extend(event.paths.iter().map(|path| FileUtils::document_key(path))),
+            }
+        }
+    });
+
+    Ok(watcher)
+}
+
+/// The watch state, starting the watcher on first use.
+fn started_state<'a>(
+    guard: &'a mut Option<WatchState>,
+    app_handle: &AppHandle,
+) -> Option<&'a mut WatchState> {
+    if guard.is_none() {
+        match start_watcher(app_handle.clone()) {
+            Ok(watcher) => {
+                *guard = Some(WatchState {
+                    watcher,
+                    documents: HashMap::new(),
+                    dirs: HashMap::new(),
+                })
+            }
+            Err(e) => {
+                println!("[WATCHER] {}", e);
+                return None;
+            }
+        }
+    }
+    guard.as_mut()
+}
+
+/// Start watching `path`, remembering `content` as the version Clareza
+/// knows and `bytes` as how it is stored on disk. Called after every open;
+/// failures are logged only.
+pub async fn track_document(app_handle: &AppHandle, path: &Path, bytes: &[u8], content: &str) {
+    let key = FileUtils::document_key(path);
+    let mut guard = WATCH_STATE.lock().await;
+    if let Some(state) = started_state(&mut guard, app_handle) {
+        state.track(&key, bytes, content);
+    }
+}
+
+/// Check the file on disk before `content` is written over it and return
+/// what to write: `content` itself, or the result of a clean merge.
+async fn resolve_conflict(
+    key: &Path,
+    document: &TrackedDocument,
+    content: &str,
+    strategy: ConflictStrategy,
+) -> Result<String, ClarezaError> {
+    let Ok(disk) = tokio::fs::read(key).await else {
+        // Deleted meanwhile: saving recreates it
+        return Ok(content.to_string());
+    };
+    if content_hash(&disk) == document.hash {
+        return Ok(content.to_string());
+    }
+
+    match strategy {
+        ConflictStrategy::Overwrite => {
+            println!(
+                "[WATCHER] Overwriting external changes in {}",
+                key.display()
+            );
+            Ok(content.to_string())
+        }
+        ConflictStrategy::Refuse => Err(ClarezaError::Conflict(format!(
+            "{} was changed by another program since it was opened",
+            key.display()
+        ))),
+        ConflictStrategy::Merge => {
+            let disk = encoding::normalize_line_endings(&encoding::decode(&disk).content);
+            let result = merge_texts(&document.base, content, &disk, OUR_LABEL, THEIR_LABEL);
+            if result.conflicts > 0 {
+                return Err(ClarezaError::Conflict(format!(
+                    "{} has {} conflicting changes with the version on disk",
+                    key.display(),
+                    result.conflicts
+                )));
+            }
+            println!("[WATCHER] Merged external changes into {}", key.display());
+            Ok(result.merged)
+        }
+    }
+}
+
+/// Save `content` to `path`, resolving changes made on disk meanwhile with
+/// `strategy`, and return the text written (merged, possibly). `encode`
+/// turns that text into the bytes stored.
+///
+/// The new hash is tracked before the file is replaced, and the watch state
+/// stays locked until it is, so the watcher cannot mistake the save for an
+/// external change however long it takes.
+pub async fn save_tracked(
+    app_handle: &AppHandle,
+    path: &Path,
+    content: &str,
+    strategy: ConflictStrategy,
+    encode: impl FnOnce(&str) -> Result<Vec<u8>, ClarezaError>,
+) -> Result<String, ClarezaError> {
+    let key = FileUtils::document_key(path);
+    let mut guard = WATCH_STATE.lock().await;
+    let written = match guard.as_ref().and_then(|s| s.documents.get(&key)) {
+        Some(document) => resolve_conflict(&key, document, content, strategy).await?,
+        None => content.to_string(),
+    };
+    let bytes = encode(&written)?;
+
+    let previous =
+        started_state(&mut guard, app_handle).map(|state| state.track(&key, &bytes, &written));
+    if let Err(e) = FileUtils::atomic_write_bytes(path, &bytes).await {
+        // The file still holds what was tracked before
+        if let (Some(state), Some(previous)) = (guard.as_mut(), previous) {
+            match previous {
+                Some(document) => {
+                    state.documents.insert(key, document);
+                }
+                None => {
+                    state.documents.remove(&key);
+                    state.unwatch_dir(&key);
+                }
+            }
+        }
+        return Err(e);
+    }
+    Ok(written)
+}
+
+/// Merge `content` with the current disk version of `path` without saving,
+/// so the user can review conflicts before choosing how to save.
+#[tauri::command]
+pub async fn merge_with_disk(path: String, content: String) -> Result<MergeResult, ClarezaError> {
+    let key = FileUtils::document_key(Path::new(&path));
+    let disk = encoding::normalize_line_endings(&FileUtils::read_with_encoding(&key).await?);
+    let guard = WATCH_STATE.lock().await;
+    let base = guard
+        .as_ref()
+        .and_then(|s| s.documents.get(&key))
+        .map(|document| document.base.as_str())
+        .ok_or_else(|| ClarezaError::FileNotFound(format!("{} is not being watched", path)))?;
+    Ok(merge_texts(base, &content, &disk, OUR_LABEL, THEIR_LABEL))
+}
+
+/// Stop watching a document, typically when it is closed.
+#[tauri::command]
+pub async fn unwatch_document(path: String) -> Result<(), ClarezaError> {
+    let key = FileUtils::document_key(Path::new(&path));
+    let mut guard = WATCH_STATE.lock().await;
+    if let Some(state) = guard.as_mut() {
+        if state.documents.remove(&key).is_some() {
+            state.unwatch_dir(&key);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,102 @@
+// src-tauri/src/workspace.rs
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use uuid::Uuid;
+
+use crate::errors::AiError;
+
+const WORKSPACES_DIR: &str = "clareza-workspaces";
+/// No job runs this long, older workspaces were left behind by a crash.
+const STALE_AFTER: Duration = Duration::from_secs(60 * 60);
+
+fn workspaces_root() -> PathBuf {
+    std::env::temp_dir().join(WORKSPACES_DIR)
+}
+
+/// Private temporary directory an engine call runs in, holding the files its
+/// prompt refers to.
+///
+/// Removed when dropped, which covers completion, failure and cancellation
+/// (cancelling a job drops its future). Leftovers from a crash are removed by
+/// `purge_stale_workspaces` on the next start.
+pub struct JobWorkspace {
+    path: PathBuf,
+}
+
+impl JobWorkspace {
+    pub async fn create(job_id: &str) -> Result<Self, AiError> {
+        let root = workspaces_root();
+        tokio::fs::create_dir_all(&root)
+            .await
+            .map_err(|e| AiError::Other(format!("Failed to create workspace root: {}", e)))?;
+
+        // Unique per call: chunks and retries of one job run side by side
+        let path = root.join(format!("{}-{}", job_id, Uuid::new_v4().simple()));
+        let mut builder = tokio::fs::DirBuilder::new();
+        #[cfg(unix)]
+        builder.mode(0o700);
+        builder
+            .create(&path)
+            .await
+            .map_err(|e| AiError::Other(format!("Failed to create job workspace: {}", e)))?;
+
+        println!("[WORKSPACE] Created {}", path.display());
+        Ok(Self { path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Write a file at the top of the workspace. `name` must be a plain file
+    /// name.
+    pub async fn write_file(&self, name: &str, content: &str) -> Result<(), AiError> {
+        if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+            return Err(AiError::Other(format!(
+                "Invalid workspace file name '{}'",
+                name
+            )));
+        }
+        tokio::fs::write(self.path.join(name), content)
+            .await
+            .map_err(|e| AiError::Other(format!("Failed to write workspace file: {}", e)))
+    }
+}
+
+impl Drop for JobWorkspace {
+    fn drop(&mut self) {
+        match std::fs::remove_dir_all(&self.path) {
+            Ok(()) => println!("[WORKSPACE] Removed {}", self.path.display()),
+            Err(e) => println!(
+                "[WORKSPACE] Could not remove {}: {}",
+                self.path.display(),
+                e
+            ),
+        }
+    }
+}
+
+/// Remove workspaces left behind by a previous run that did not exit
+/// cleanly. Recent ones may belong to another running instance and are kept.
+pub fn purge_stale_workspaces() {
+    let Ok(entries) = std::fs::read_dir(workspaces_root()) else {
+        return;
+    };
+
+    let mut removed = 0;
+    for entry in entries.flatten() {
+        let is_stale = entry
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .is_some_and(|age| age > STALE_AFTER);
+        if is_stale && std::fs::remove_dir_all(entry.path()).is_ok() {
+            removed += 1;
+        }
+    }
+    if removed > 0 {
+        println!("[WORKSPACE] Removed {} stale workspaces", removed);
+    }
+}
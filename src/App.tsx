@@ -8,7 +8,7 @@ import { useFileHandler } from './hooks/useFileHandler';
 import { useToast } from './hooks/useToast';
 import { TOOLS, DEFAULT_CONTENT } from './constants';
 import { invoke } from '@tauri-apps/api/core';
-import { FileService } from './services/fileService';
+import { FileService, ExternalChange, FileOperation, isConflictError } from './services/fileService';
 
 import Header from './components/Header';
 import Sidebar from './components/Sidebar';
@@ -45,6 +45,7 @@ export default function ClarezaApp() {
     goToPreviousVersion,
     goToNextVersion,
     setContentChanged,
+    noteExternalChange,
   } = useFileHandler();
 
   const { toasts, addToast, removeToast } = useToast();
@@ -81,7 +82,7 @@ export default function ClarezaApp() {
             console.log('[FRONTEND] Attempting to save as new version...');
 
             try {
-              const result = await saveFile(geminiResponse);
+              const result = await saveResolvingConflicts(geminiResponse);
               console.log('[FRONTEND] Save result:', result);
               addToast('Nova versão criada com sugestões da IA', 'success');
             } catch (error) {
@@ -114,6 +115,44 @@ export default function ClarezaApp() {
     };
   }, [currentFilePath, addToast]);
 
+  useEffect(() => {
+    let unlistenFn: (() => void) | null = null;
+
+    listen<ExternalChange>('document-changed-externally', async (event) => {
+      const change = event.payload;
+      if (!currentFilePath || change.path !== currentFilePath) return;
+      noteExternalChange(change);
+
+      if (change.kind === 'renamed') {
+        addToast(`O arquivo foi renomeado para ${change.new_path}`, 'success');
+      } else if (change.kind === 'deleted') {
+        addToast('O arquivo foi apagado por outro programa. Salve para recriá-lo.', 'error');
+      } else if (!isDirty) {
+        // Nothing to lose, show what is on disk now
+        try {
+          const result = await openFile(currentFilePath);
+          if (result.success && result.content !== undefined) {
+            setEditorContent(result.content);
+            addToast('Arquivo recarregado: foi alterado por outro programa', 'success');
+          }
+        } catch (error) {
+          console.error('Failed to reload file:', error);
+        }
+      } else {
+        addToast(
+          'O arquivo foi alterado por outro programa. Ao salvar, você poderá mesclar ou sobrescrever.',
+          'error',
+        );
+      }
+    }).then((fn) => {
+      unlistenFn = fn;
+    });
+
+    return () => {
+      if (unlistenFn) unlistenFn();
+    };
+  }, [currentFilePath, isDirty, addToast]);
+
   useEffect(() => {
     if (lastAutoSave) {
       setShowAutoSaveIndicator(true);
@@ -271,6 +310,41 @@ export default function ClarezaApp() {
     }
   };
 
+  /**
+   * Save, asking the user what to do when another program changed the file
+   * since it was opened. A merged result replaces the editor content.
+   */
+  const saveResolvingConflicts = async (content: string, asNew = false): Promise<FileOperation> => {
+    let result: FileOperation;
+    try {
+      result = await saveFile(content, asNew);
+    } catch (error) {
+      if (!isConflictError(error)) throw error;
+
+      const merge = confirm(
+        'O arquivo foi alterado por outro programa desde que foi aberto.\n\n' +
+          'OK: mesclar as suas alterações com a versão do disco\n' +
+          'Cancelar: escolher se deseja sobrescrever a versão do disco',
+      );
+      try {
+        if (!merge) throw error;
+        result = await saveFile(content, asNew, undefined, 'merge');
+      } catch (mergeError) {
+        if (!isConflictError(mergeError)) throw mergeError;
+        const message = merge
+          ? 'Não foi possível mesclar: os mesmos trechos foram alterados nos dois lados.\n\n'
+          : '';
+        if (!confirm(`${message}Deseja sobrescrever a versão do disco com o seu texto?`)) throw mergeError;
+        result = await saveFile(content, asNew, undefined, 'overwrite');
+      }
+    }
+
+    if (result.success && result.content !== undefined) {
+      setEditorContent(result.content);
+    }
+    return result;
+  };
+
   const handleSave = async () => {
     if (!isDirty && currentFilePath) {
       addToast('Nenhuma alteração para salvar', 'success');
@@ -278,7 +352,7 @@ export default function ClarezaApp() {
     }
 
     try {
-      const result = await saveFile(editorContent);
+      const result = await saveResolvingConflicts(editorContent);
       addToast(result.message, 'success');
     } catch (error) {
       console.error('Failed to save file:', error);
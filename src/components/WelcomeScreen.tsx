@@ -5,8 +5,10 @@ import { getVersion } from '@tauri-apps/api/app';
 
 interface RecentFile {
     path: string;
-    name: string;
+    title: string;
     last_opened: string;
+    exists: boolean;
+    pinned: boolean;
 }
 
 interface WelcomeScreenProps {
@@ -111,7 +113,7 @@ export default function WelcomeScreen({
                                         <FileText className="w-5 h-5 text-gray-500 group-hover:text-blue-400 transition-colors" />
                                         <div>
                                             <div className="font-medium text-gray-200 group-hover:text-white transition-colors">
-                                                {file.name}
+                                                {file.title}
                                             </div>
                                             <div className="text-xs text-gray-500 truncate max-w-[300px]">
                                                 {file.path}
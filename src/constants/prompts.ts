@@ -14,6 +14,6 @@ export const PROMPTS = {
 
   otherSuggestions: `Analise o texto do arquivo destacado em português do Brasil para verificar consistência, fluxo lógico e clareza narrativa. Não reescreva integralmente o texto. Apresente recomendações objetivas sobre possíveis melhorias estruturais ou de organização de ideias, sempre preservando a intenção do autor. O resultado deve ser apenas uma lista de sugestões pontuais, não uma versão reescrita. @file_reference`,
 
-  toneAdjustment: `Reescreva o texto do arquivo destacado em português do Brasil, ajustando exclusivamente o tom da escrita conforme a solicitação do usuário (ex.: mais formal, mais casual, mais persuasivo, mais neutro). Mantenha integralmente a ideia e o conteúdo original, sem adicionar ou remover informações. Não altere a gramática correta, apenas adapte o tom da narrativa. O resultado deve ser o mesmo texto, preservando a voz do autor, mas com o tom solicitado. @file_reference`,
+  toneAdjustment: `Reescreva o texto do arquivo destacado em português do Brasil, ajustando exclusivamente o tom da escrita {{#if tone}}para um tom {{tone}}{{else}}conforme a solicitação do usuário (ex.: mais formal, mais casual, mais persuasivo, mais neutro){{/if}}. Mantenha integralmente a ideia e o conteúdo original, sem adicionar ou remover informações. Não altere a gramática correta, apenas adapte o tom da narrativa. O resultado deve ser o mesmo texto, preservando a voz do autor, mas com o tom solicitado. @file_reference`,
 
 };
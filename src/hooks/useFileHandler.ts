@@ -1,9 +1,21 @@
 // src/hooks/useFileHandler.ts
 import { useState, useCallback, useRef, useEffect } from 'react';
-import { FileService, FileOperation, DocumentMetadata } from '../services/fileService';
+import {
+  FileService,
+  FileOperation,
+  DocumentMetadata,
+  ConflictStrategy,
+  ExternalChange,
+  isConflictError,
+} from '../services/fileService';
 
 interface FileHandlerState {
   currentFilePath: string | undefined;
+  isReadOnly: boolean;
+  /** Encoding the file is stored in, kept when saving */
+  encoding: string | undefined;
+  /** Changed on disk by another program since it was opened or saved */
+  hasExternalChanges: boolean;
   isDirty: boolean;
   metadata: DocumentMetadata | undefined;
   isLoading: boolean;
@@ -12,9 +24,22 @@ interface FileHandlerState {
   currentVersionIndex: number;
 }
 
+/** Release the lock and the watch held on a document that is no longer open */
+function leaveDocument(path: string) {
+  FileService.releaseDocumentLock(path).catch((error) =>
+    console.error('Failed to release document lock:', error),
+  );
+  FileService.unwatchDocument(path).catch((error) =>
+    console.error('Failed to unwatch document:', error),
+  );
+}
+
 export function useFileHandler() {
   const [state, setState] = useState<FileHandlerState>({
     currentFilePath: undefined,
+    isReadOnly: false,
+    encoding: undefined,
+    hasExternalChanges: false,
     isDirty: false,
     metadata: undefined,
     isLoading: false,
@@ -36,9 +61,15 @@ export function useFileHandler() {
       }
 
       autoSaveTimer.current = setTimeout(async () => {
-        if (state.currentFilePath && state.isDirty) {
+        // A conflict with the disk version needs the user, see saveFile
+        if (state.currentFilePath && state.isDirty && !state.isReadOnly && !state.hasExternalChanges) {
           try {
-            await FileService.saveDocument(state.currentFilePath, pendingContent.current, state.metadata);
+            await FileService.saveDocument(
+              state.currentFilePath,
+              pendingContent.current,
+              state.metadata,
+              state.encoding,
+            );
 
             setState((prev) => ({
               ...prev,
@@ -47,11 +78,21 @@ export function useFileHandler() {
             }));
           } catch (error) {
             console.error('Auto-save failed:', error);
+            if (isConflictError(error)) {
+              setState((prev) => ({ ...prev, hasExternalChanges: true }));
+            }
           }
         }
       }, 3000); // Auto-save after 3 seconds of inactivity
     },
-    [state.currentFilePath, state.isDirty, state.metadata],
+    [
+      state.currentFilePath,
+      state.isDirty,
+      state.isReadOnly,
+      state.hasExternalChanges,
+      state.metadata,
+      state.encoding,
+    ],
   );
 
   // Clean up auto-save timer
@@ -82,6 +123,7 @@ export function useFileHandler() {
         setState(prev => ({
           ...prev,
           currentFilePath: undefined,
+          encoding: undefined,
           isDirty: false,
           metadata: result.metadata || undefined,
           isLoading: false,
@@ -98,15 +140,34 @@ export function useFileHandler() {
   }, []); // Added missing deps array
 
   const openFile = useCallback(async (path: string): Promise<FileOperation> => {
+    // Someone else editing the file on a shared drive: offer a read-only copy
+    const lock = await FileService.checkDocumentLock(path);
+    let readOnly = false;
+    if (lock.locked) {
+      const owner = lock.owner
+        ? `${lock.owner.user} (${lock.owner.host}) desde ${new Date(lock.owner.opened_at).toLocaleString()}`
+        : 'outro programa';
+      readOnly = confirm(`Este arquivo está aberto por ${owner}.\n\nDeseja abri-lo somente para leitura?`);
+      if (!readOnly) {
+        return { success: false, message: 'Open cancelled' };
+      }
+    }
+
     setState((prev) => ({ ...prev, isLoading: true }));
 
     try {
-      const result = await FileService.openDocument(path);
+      const result = await FileService.openDocument(path, readOnly);
 
       if (result.success) {
+        if (state.currentFilePath && state.currentFilePath !== result.path) {
+          leaveDocument(state.currentFilePath);
+        }
         setState((prev) => ({
           ...prev,
           currentFilePath: result.path || undefined,
+          isReadOnly: readOnly,
+          encoding: result.encoding,
+          hasExternalChanges: false,
           isDirty: false,
           metadata: result.metadata || undefined,
           isLoading: false,
@@ -120,22 +181,41 @@ export function useFileHandler() {
       setState((prev) => ({ ...prev, isLoading: false }));
       throw error;
     }
-  }, [fetchVersions]);
+  }, [state.currentFilePath, fetchVersions]);
 
   const saveFile = useCallback(
-    async (content: string, asNew = false): Promise<FileOperation> => {
+    async (
+      content: string,
+      asNew = false,
+      encoding?: string,
+      onConflict?: ConflictStrategy,
+    ): Promise<FileOperation> => {
       setState((prev) => ({ ...prev, isLoading: true }));
 
       try {
         const result =
           asNew || !state.currentFilePath
-            ? await FileService.saveDocumentAs(content, undefined, state.metadata)
-            : await FileService.saveDocument(state.currentFilePath, content, state.metadata);
+            ? await FileService.saveDocumentAs(content, undefined, state.metadata, encoding ?? state.encoding)
+            : await FileService.saveDocument(
+                state.currentFilePath,
+                content,
+                state.metadata,
+                encoding ?? state.encoding,
+                onConflict,
+              );
 
         if (result.success) {
+          // Saved under a new name, the old file is no longer being edited
+          if (state.currentFilePath && result.path && state.currentFilePath !== result.path) {
+            leaveDocument(state.currentFilePath);
+          }
           setState(prev => ({
             ...prev,
             currentFilePath: result.path || prev.currentFilePath,
+            // Saving takes the lock, so the document is editable again
+            isReadOnly: false,
+            encoding: result.encoding || prev.encoding,
+            hasExternalChanges: false,
             isDirty: false,
             metadata: result.metadata || prev.metadata,
             isLoading: false,
@@ -150,7 +230,7 @@ export function useFileHandler() {
         throw error;
       }
     },
-    [state.currentFilePath, state.metadata, fetchVersions],
+    [state.currentFilePath, state.metadata, state.encoding, fetchVersions],
   );
 
   const createBackup = useCallback(async (): Promise<void> => {
@@ -199,6 +279,17 @@ export function useFileHandler() {
     }
   }, [state.currentFilePath, state.currentVersionIndex, state.versions]);
 
+  /** Follow the open document when another program renames or changes it */
+  const noteExternalChange = useCallback((change: ExternalChange) => {
+    setState((prev) => {
+      if (change.path !== prev.currentFilePath) return prev;
+      if (change.kind === 'renamed' && change.new_path) {
+        return { ...prev, currentFilePath: change.new_path };
+      }
+      return { ...prev, hasExternalChanges: true };
+    });
+  }, []);
+
   const setContentChanged = useCallback(
     (content: string) => {
       setState((prev) => ({ ...prev, isDirty: true }));
@@ -216,5 +307,6 @@ export function useFileHandler() {
     goToPreviousVersion,
     goToNextVersion,
     setContentChanged,
+    noteExternalChange,
   };
 }
\ No newline at end of file
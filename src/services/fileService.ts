@@ -20,6 +20,7 @@ export interface FileOperation {
   path?: string;
   content?: string;
   metadata?: DocumentMetadata;
+  encoding?: string;
 }
 
 export interface BackupInfo {
@@ -35,6 +36,34 @@ export interface RecentFile {
   title: string;
   last_opened: string;
   exists: boolean;
+  pinned: boolean;
+}
+
+export interface LockOwner {
+  user: string;
+  host: string;
+  pid: number;
+  opened_at: string;
+}
+
+export interface LockStatus {
+  locked: boolean;
+  owner?: LockOwner;
+}
+
+/** What to do when the file changed on disk since it was opened */
+export type ConflictStrategy = 'refuse' | 'overwrite' | 'merge';
+
+/** Payload of the `document-changed-externally` event */
+export interface ExternalChange {
+  path: string;
+  kind: 'modified' | 'deleted' | 'renamed';
+  new_path?: string;
+}
+
+/** Save refused because another program changed the file */
+export function isConflictError(error: unknown): boolean {
+  return String(error).includes('Conflict:');
 }
 
 export class FileService {
@@ -63,23 +92,64 @@ export class FileService {
   /**
    * Open an existing document from file system
    */
-  static async openDocument(path: string): Promise<FileOperation> {
+  static async openDocument(path: string, readOnly = false): Promise<FileOperation> {
     try {
-      return await invoke<FileOperation>('open_document', { path });
+      return await invoke<FileOperation>('open_document', { path, readOnly });
     } catch (error) {
       throw new Error(`Failed to open document: ${error}`);
     }
   }
 
+  /**
+   * Check whether someone else has the document open
+   */
+  static async checkDocumentLock(path: string): Promise<LockStatus> {
+    try {
+      return await invoke<LockStatus>('check_document_lock', { path });
+    } catch (error) {
+      throw new Error(`Failed to check document lock: ${error}`);
+    }
+  }
+
+  /**
+   * Release the lock taken when the document was opened
+   */
+  static async releaseDocumentLock(path: string): Promise<void> {
+    try {
+      await invoke('release_document_lock', { path });
+    } catch (error) {
+      throw new Error(`Failed to release document lock: ${error}`);
+    }
+  }
+
+  /**
+   * Stop watching the document for changes made by other programs
+   */
+  static async unwatchDocument(path: string): Promise<void> {
+    try {
+      await invoke('unwatch_document', { path });
+    } catch (error) {
+      throw new Error(`Failed to unwatch document: ${error}`);
+    }
+  }
+
   /**
    * Save document to existing path
    */
-  static async saveDocument(path: string, content: string, metadata?: DocumentMetadata): Promise<FileOperation> {
+  static async saveDocument(
+    path: string,
+    content: string,
+    metadata?: DocumentMetadata,
+    encoding?: string,
+    onConflict?: ConflictStrategy,
+  ): Promise<FileOperation> {
     try {
       return await invoke<FileOperation>('save_document', {
         path,
         content,
         ...(metadata ? { metadata } : {}),
+        ...(encoding ? { encoding } : {}),
+        ...(onConflict ? { onConflict } : {}),
       });
     } catch (error) {
       throw new Error(`Failed to save document: ${error}`);
@@ -93,12 +163,14 @@ export class FileService {
     content: string,
     suggestedName?: string,
     metadata?: DocumentMetadata,
+    encoding?: string,
   ): Promise<FileOperation> {
     try {
       return await invoke<FileOperation>('save_document_as', {
         content,
         suggested_name: suggestedName || null,
         ...(metadata ? { metadata } : {}),
+        ...(encoding ? { encoding } : {}),
       });
     } catch (error) {
       throw new Error(`Failed to save document as: ${error}`);